@@ -1,20 +1,29 @@
 pub mod error;
+pub mod semantics;
 mod subgraph;
+pub mod syntax;
 
 use self::{
-    error::{GraphError, GraphResult},
+    error::{GraphError, GraphResult, InvariantViolation},
     subgraph::Subgraph,
 };
 use crate::{
     atom::Atom,
-    proof::action::{error::ActionError, Action, GraphTarget},
+    proof::{
+        action::{
+            error::{ActionError, ActionResult},
+            Action, GraphTarget,
+        },
+        rule::{escape_field, unescape_field},
+    },
 };
 use hashlink::{LinkedHashMap, LinkedHashSet};
 use itertools::Itertools;
 use nid::Nanoid;
 use std::{
-    collections::{HashMap, HashSet, LinkedList},
+    collections::{hash_map::RandomState, HashMap, HashSet, LinkedList, VecDeque},
     fmt::{Debug, Write},
+    hash::{BuildHasher, Hasher},
     mem,
     sync::Arc,
 };
@@ -28,12 +37,22 @@ const ID_LEN: usize = 3;
 
 pub type GraphKey = Nanoid<ID_LEN>;
 
+/// maps atoms to the graphs that should take their place
+pub type Substitution = HashMap<Atom, Graph>;
+
+/// the symbols nanoids are made of
+const KEY_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_-";
+
 #[derive(Clone)]
 pub struct Graph {
     root_id: GraphKey,
     known_atoms: HashMap<Arc<Atom>, usize>,
     subgraphs: LinkedHashMap<GraphKey, Subgraph>,
     subgraph_parents: HashMap<GraphKey, GraphKey>,
+
+    /// state used to generate new keys; clones generate the same keys as the original when
+    /// given the same actions, so work done on a clone can be replayed on the original
+    key_state: u64,
 }
 
 impl Graph {
@@ -46,6 +65,7 @@ impl Graph {
             known_atoms: Default::default(),
             subgraphs: Default::default(),
             subgraph_parents: Default::default(),
+            key_state: RandomState::new().build_hasher().finish(),
         };
 
         g.subgraphs.insert(root_id, Subgraph::new(0));
@@ -57,11 +77,29 @@ impl Graph {
         &self.root_id
     }
 
+    /// the state new keys are generated from
+    pub fn key_state(&self) -> u64 {
+        self.key_state
+    }
+
+    /// the same graph, but generating keys from `key_state`; two graphs with the same contents
+    /// and key state generate the same keys when given the same actions
+    pub fn with_key_state(mut self, key_state: u64) -> Self {
+        self.key_state = key_state;
+        self
+    }
+
     #[instrument]
     pub fn atoms(&self) -> impl Iterator<Item = &Arc<Atom>> {
         self.known_atoms.keys()
     }
 
+    /// the id of every subgraph, in the order they were added
+    #[instrument]
+    pub fn subgraph_keys(&self) -> impl DoubleEndedIterator<Item = &GraphKey> {
+        self.subgraphs.keys()
+    }
+
     #[instrument]
     pub fn atoms_of(&self, target: &GraphKey) -> GraphResult<&HashSet<Arc<Atom>>> {
         self.subgraphs
@@ -100,15 +138,18 @@ impl Graph {
         } else if !self.subgraphs.contains_key(lower) {
             Err(GraphError::InvalidSubgraphTargetError(lower.to_string()))?
         } else {
-            let current = lower;
+            let mut current = lower;
 
-            while let Ok(thing) = self.parent_of(current) {
-                if current == higher || thing == higher {
-                    return Ok(true);
+            loop {
+                if current == higher {
+                    break Ok(true);
                 }
-            }
 
-            Ok(false)
+                match self.subgraph_parents.get(current) {
+                    Some(parent) => current = parent,
+                    None => break Ok(false),
+                }
+            }
         }
     }
 
@@ -118,9 +159,16 @@ impl Graph {
         target: &GraphKey,
         atom: impl Into<Atom> + Debug,
     ) -> GraphResult<()> {
-        if self.subgraphs.contains_key(target) {
+        if let Some(subgraph) = self.subgraphs.get(target) {
             let atom = Arc::new(atom.into());
 
+            if subgraph.atoms().contains(&atom) {
+                Err(GraphError::DuplicateAtomError(
+                    atom.to_string(),
+                    target.to_string(),
+                ))?
+            }
+
             self.known_atoms
                 .entry(atom.clone())
                 .and_modify(|e| *e += 1)
@@ -165,7 +213,8 @@ impl Graph {
 
     #[instrument]
     pub fn insert_subgraph(&mut self, target: &GraphKey) -> GraphResult<GraphKey> {
-        self.insert_subgraph_with_id(self.gen_unique_unused_key(), target)
+        let id = self.gen_unique_unused_key();
+        self.insert_subgraph_with_id(id, target)
     }
 
     #[instrument]
@@ -183,10 +232,14 @@ impl Graph {
             }
         }
 
-        if !self.subgraphs_of(src).unwrap().contains(target) {
+        if !self.subgraphs_of(src)?.contains(target) {
             Err(GraphError::InvalidSubgraphTargetError(target.to_string()))?
         }
 
+        if self.is_related_to(target, dest)? {
+            Err(GraphError::CyclicMoveError(target.to_string()))?
+        }
+
         self.subgraph_parents.insert(*target, *dest);
 
         self.subgraphs
@@ -201,8 +254,14 @@ impl Graph {
             .subgraphs_mut()
             .insert(*target);
 
-        let new_level = self.subgraphs.get(dest).unwrap().level() + 1;
-        self.subgraphs.get_mut(target).unwrap().set_level(new_level);
+        // everything below the moved graph shifts along with it
+        let mut queue = VecDeque::from([(*target, self.subgraphs.get(dest).unwrap().level() + 1)]);
+        while let Some((id, new_level)) = queue.pop_front() {
+            let subgraph = self.subgraphs.get_mut(&id).unwrap();
+            subgraph.set_level(new_level);
+
+            queue.extend(subgraph.subgraphs().iter().map(|c| (*c, new_level + 1)));
+        }
 
         Ok(())
     }
@@ -213,16 +272,18 @@ impl Graph {
         target: &GraphKey,
         atom: &Atom,
     ) -> GraphResult<Option<Atom>> {
-        if self.subgraphs.contains_key(target) {
-            self.subgraphs
-                .get_mut(target)
-                .unwrap()
-                .atoms_mut()
-                .remove(atom);
+        match self.subgraphs.get_mut(target) {
+            Some(subgraph) => {
+                if !subgraph.atoms_mut().remove(atom) {
+                    Err(GraphError::InvalidAtomTargetError(
+                        atom.to_string(),
+                        target.to_string(),
+                    ))?
+                }
 
-            Ok(self.decrement_atom(atom))
-        } else {
-            Err(GraphError::InvalidSubgraphTargetError(target.to_string()))
+                self.decrement_atom(atom)
+            }
+            None => Err(GraphError::InvalidSubgraphTargetError(target.to_string())),
         }
     }
 
@@ -237,57 +298,307 @@ impl Graph {
         {
             Err(GraphError::InvalidSubgraphTargetError(target.to_string()))
         } else {
-            let parent_id = self.subgraph_parents.remove(target).unwrap();
+            let parent_id = *self.parent_of(target)?;
+
+            if !self
+                .subgraphs
+                .get(&parent_id)
+                .is_some_and(|p| p.subgraphs().contains(target))
+            {
+                Err(GraphError::InvariantViolationError(vec![
+                    InvariantViolation::NotAChildOfParent {
+                        id: target.to_string(),
+                        parent: parent_id.to_string(),
+                    },
+                ]))?
+            }
 
-            if let Some(x) = self.subgraphs.get_mut(&parent_id) {
-                if !x.subgraphs_mut().remove(target) {
-                    panic!("Parent graph does not have target registered as child")
-                }
+            // children go first, so a failure leaves the target itself in place
+            for c in self.subgraphs_of(target)?.clone() {
+                self.remove_subgraph(&c, recursive)?;
             }
 
-            let mut removed_graph = self.subgraphs.remove(target).unwrap();
+            let mut removed_graph = self
+                .subgraphs
+                .remove(target)
+                .ok_or_else(|| GraphError::InvalidSubgraphTargetError(target.to_string()))?;
 
-            mem::take(removed_graph.atoms_mut())
-                .into_iter()
-                .for_each(|v| {
-                    self.decrement_atom(&v);
-                });
+            self.subgraph_parents.remove(target);
+            if let Some(x) = self.subgraphs.get_mut(&parent_id) {
+                x.subgraphs_mut().remove(target);
+            }
 
-            mem::take(removed_graph.subgraphs_mut())
-                .into_iter()
-                .for_each(|c| {
-                    self.remove_subgraph(&c, recursive).unwrap();
-                });
+            for v in mem::take(removed_graph.atoms_mut()) {
+                self.decrement_atom(&v)?;
+            }
 
             Ok(())
         }
     }
 
     #[instrument]
-    pub(crate) fn gen_unique_unused_key(&self) -> GraphKey {
+    pub(crate) fn gen_unique_unused_key(&mut self) -> GraphKey {
         loop {
-            let gen_id = GraphKey::new();
+            // splitmix64
+            self.key_state = self.key_state.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = self.key_state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            z ^= z >> 31;
+
+            let gen_id = (0..ID_LEN)
+                .map(|i| KEY_ALPHABET[((z >> (i * 6)) & 63) as usize] as char)
+                .collect::<String>();
+
+            if let Ok(gen_id) = GraphKey::try_from_str(&gen_id) {
+                if !self.subgraphs.contains_key(&gen_id) {
+                    break gen_id;
+                }
+            }
+        }
+    }
 
-            if !self.subgraphs.contains_key(&gen_id) {
-                break gen_id;
+    /// generates the actions that replace every occurrence of an atom that has a substitute with
+    /// the contents of that substitute
+    ///
+    /// the substitution is simultaneous, so atoms brought in by a substitute are left alone
+    #[instrument]
+    pub fn substitute(&self, substitution: &Substitution) -> ActionResult<LinkedList<Action>> {
+        let mut scratch = self.clone();
+        let mut ans = LinkedList::new();
+        let mut offset = 0;
+
+        let mut queue = VecDeque::from([self.root_id]);
+        while let Some(id) = queue.pop_front() {
+            queue.extend(self.subgraphs_of(&id)?.iter().cloned());
+
+            let substituted = self
+                .atoms_of(&id)?
+                .iter()
+                .filter_map(|a| Some((a, substitution.get(&**a)?)))
+                .collect_vec();
+
+            // everything is removed before anything is added, so that swapping atoms works
+            let mut actions = substituted
+                .iter()
+                .map(|(a, _)| Action::DeleteAtom {
+                    target: GraphTarget::Exists(id),
+                    atom: (***a).clone(),
+                })
+                .collect::<LinkedList<_>>();
+            Action::apply_actions(actions.clone(), &mut scratch)?;
+            ans.append(&mut actions);
+
+            for (_, substitute) in substituted {
+                let mut actions = parse_graph_string_into_actions(
+                    &scratch,
+                    &transform_graph_into_string(substitute, substitute.root_id())?,
+                    id,
+                )?
+                .into_iter()
+                .map(|v| v.shift_future_targets(offset))
+                .collect::<LinkedList<_>>();
+
+                offset += actions
+                    .iter()
+                    .filter(|v| {
+                        matches!(
+                            v,
+                            Action::AddSubgraph {
+                                new_subgraph: GraphTarget::Future(_),
+                                ..
+                            }
+                        )
+                    })
+                    .count();
+
+                Action::apply_actions(actions.clone(), &mut scratch)?;
+                ans.append(&mut actions);
             }
         }
+
+        Ok(ans)
     }
 
+    /// generates the actions that rename every occurrence of `from` to `to`
     #[instrument]
-    fn decrement_atom(&mut self, atom: &Atom) -> Option<Atom> {
-        *self.known_atoms.get_mut(atom).unwrap() -= 1;
+    pub fn rename_atom(&self, from: &Atom, to: Atom) -> ActionResult<LinkedList<Action>> {
+        let mut substitute = Graph::new();
+        substitute.insert_atom(&substitute.root_id.clone(), to)?;
 
-        if self.known_atoms.get(atom).unwrap() < &1_usize {
-            self.known_atoms
+        self.substitute(&HashMap::from([(from.clone(), substitute)]))
+    }
+
+    /// verifies that the internal bookkeeping of the graph is consistent,
+    /// returning every violation found
+    #[instrument]
+    pub fn check_invariants(&self) -> GraphResult<()> {
+        let mut violations = Vec::new();
+
+        for (id, subgraph) in &self.subgraphs {
+            match self.subgraph_parents.get(id) {
+                Some(parent) if id == &self.root_id => {
+                    violations.push(InvariantViolation::RootHasParent {
+                        parent: parent.to_string(),
+                    })
+                }
+                Some(parent) => match self.subgraphs.get(parent) {
+                    Some(p) if !p.subgraphs().contains(id) => {
+                        violations.push(InvariantViolation::NotAChildOfParent {
+                            id: id.to_string(),
+                            parent: parent.to_string(),
+                        })
+                    }
+                    Some(_) => (),
+                    None => violations.push(InvariantViolation::ParentDoesNotExist {
+                        id: id.to_string(),
+                        parent: parent.to_string(),
+                    }),
+                },
+                None if id != &self.root_id => {
+                    violations.push(InvariantViolation::MissingParent { id: id.to_string() })
+                }
+                None => (),
+            }
+
+            for child in subgraph.subgraphs() {
+                if !self.subgraphs.contains_key(child) {
+                    violations.push(InvariantViolation::ChildDoesNotExist {
+                        id: id.to_string(),
+                        child: child.to_string(),
+                    })
+                } else if self.subgraph_parents.get(child) != Some(id) {
+                    violations.push(InvariantViolation::ChildHasOtherParent {
+                        id: id.to_string(),
+                        child: child.to_string(),
+                        parent: self.subgraph_parents.get(child).map(|v| v.to_string()),
+                    })
+                }
+            }
+        }
+
+        for id in self.subgraph_parents.keys() {
+            if !self.subgraphs.contains_key(id) {
+                violations.push(InvariantViolation::DanglingParentEntry { id: id.to_string() })
+            }
+        }
+
+        // walk down from the root, checking levels and counting atoms as we go
+        let mut visited = HashSet::new();
+        let mut atom_counts: HashMap<&Atom, usize> = HashMap::new();
+        let mut queue = VecDeque::from([(self.root_id, 0)]);
+
+        while let Some((id, depth)) = queue.pop_front() {
+            let Some(subgraph) = self.subgraphs.get(&id) else {
+                continue;
+            };
+
+            if !visited.insert(id) {
+                continue;
+            }
+
+            if subgraph.level() != depth {
+                violations.push(InvariantViolation::WrongLevel {
+                    id: id.to_string(),
+                    level: subgraph.level(),
+                    depth,
+                })
+            }
+
+            for atom in subgraph.atoms() {
+                *atom_counts.entry(atom).or_default() += 1;
+            }
+
+            for child in subgraph.subgraphs() {
+                queue.push_back((*child, depth + 1));
+            }
+        }
+
+        for id in self.subgraphs.keys() {
+            if !visited.contains(id) {
+                violations.push(InvariantViolation::Unreachable { id: id.to_string() });
+
+                // still count the atoms so unreachable graphs don't also show up as wrong counts
+                for atom in self.subgraphs.get(id).unwrap().atoms() {
+                    *atom_counts.entry(atom).or_default() += 1;
+                }
+            }
+        }
+
+        for (atom, recorded) in &self.known_atoms {
+            let actual = atom_counts.remove(&**atom).unwrap_or(0);
+
+            if *recorded != actual {
+                violations.push(InvariantViolation::WrongAtomCount {
+                    atom: atom.to_string(),
+                    recorded: *recorded,
+                    actual,
+                })
+            }
+        }
+
+        for (atom, actual) in atom_counts {
+            violations.push(InvariantViolation::WrongAtomCount {
+                atom: atom.to_string(),
+                recorded: 0,
+                actual,
+            })
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(GraphError::InvariantViolationError(violations))
+        }
+    }
+
+    #[instrument]
+    fn decrement_atom(&mut self, atom: &Atom) -> GraphResult<Option<Atom>> {
+        let count = self
+            .known_atoms
+            .get_mut(atom)
+            .ok_or_else(|| GraphError::UnknownAtomError(atom.to_string()))?;
+
+        *count = count.saturating_sub(1);
+
+        if *count < 1 {
+            Ok(self
+                .known_atoms
                 .remove_entry(atom)
-                .map(|(k, _)| Arc::into_inner(k).unwrap())
+                .map(|(k, _)| Arc::unwrap_or_clone(k)))
         } else {
-            None
+            Ok(None)
         }
     }
 }
 
+/// ways of breaking a graph on purpose, so that [`Graph::check_invariants`] can be tested
+#[cfg(test)]
+impl Graph {
+    pub(crate) fn set_level_unchecked(&mut self, id: &GraphKey, level: usize) {
+        if let Some(subgraph) = self.subgraphs.get_mut(id) {
+            subgraph.set_level(level);
+        }
+    }
+
+    pub(crate) fn set_parent_unchecked(&mut self, id: GraphKey, parent: Option<GraphKey>) {
+        match parent {
+            Some(parent) => self.subgraph_parents.insert(id, parent),
+            None => self.subgraph_parents.remove(&id),
+        };
+    }
+
+    pub(crate) fn remove_child_unchecked(&mut self, id: &GraphKey, child: &GraphKey) {
+        if let Some(subgraph) = self.subgraphs.get_mut(id) {
+            subgraph.subgraphs_mut().remove(child);
+        }
+    }
+
+    pub(crate) fn set_atom_count_unchecked(&mut self, atom: Atom, count: usize) {
+        self.known_atoms.insert(Arc::new(atom), count);
+    }
+}
+
 impl Default for Graph {
     #[instrument]
     fn default() -> Self {
@@ -295,9 +606,11 @@ impl Default for Graph {
     }
 }
 
-impl From<&Graph> for String {
+impl TryFrom<&Graph> for String {
+    type Error = GraphError;
+
     #[instrument]
-    fn from(graph: &Graph) -> Self {
+    fn try_from(graph: &Graph) -> Result<Self, Self::Error> {
         transform_graph_into_string(graph, graph.root_id())
     }
 }
@@ -309,6 +622,15 @@ impl TryFrom<&str> for Graph {
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         let mut graph = Graph::new();
 
+        // a keyed string also gives the id of the root
+        if let Some((key, _)) = value.trim_start().split_once(":[") {
+            if let Ok(key) = GraphKey::try_from_str(key) {
+                graph.subgraphs.remove(&graph.root_id);
+                graph.subgraphs.insert(key, Subgraph::new(0));
+                graph.root_id = key;
+            }
+        }
+
         let actions = parse_graph_string_into_actions(&graph, value, *graph.root_id())?;
 
         Action::apply_actions(actions, &mut graph)?;
@@ -329,20 +651,105 @@ impl Debug for Graph {
 }
 
 #[instrument]
-pub fn transform_graph_into_string(graph: &Graph, id: &GraphKey) -> String {
-    format!(
-        "[{}]",
-        Iterator::chain(
-            graph.atoms_of(id).unwrap().iter().map(|e| e.to_string()),
-            graph
-                .subgraphs_of(id)
-                .unwrap()
-                .iter()
-                .map(|e| transform_graph_into_string(graph, e))
-        )
-        .intersperse(",".to_string())
-        .collect::<String>()
-    )
+pub fn transform_graph_into_string(graph: &Graph, id: &GraphKey) -> GraphResult<String> {
+    write_graph_string(graph, id, None, false, false)
+}
+
+/// like [`transform_graph_into_string`], but with atoms and subgraphs sorted so that
+/// structurally identical graphs always produce the same string
+#[instrument]
+pub fn transform_graph_into_canonical_string(graph: &Graph, id: &GraphKey) -> GraphResult<String> {
+    write_graph_string(graph, id, None, true, false)
+}
+
+/// like [`transform_graph_into_string`], but every subgraph is prefixed with its id, as in
+/// `abc:[A, def:[B]]`, so that parsing the string back gives the same ids
+#[instrument]
+pub fn transform_graph_into_keyed_string(graph: &Graph, id: &GraphKey) -> GraphResult<String> {
+    write_graph_string(graph, id, None, false, true)
+}
+
+/// like [`transform_graph_into_string`], but with every atom that has a substitution replaced
+/// by the contents of its substitute
+#[instrument]
+pub fn transform_substituted_graph_into_string(
+    graph: &Graph,
+    id: &GraphKey,
+    substitution: &Substitution,
+) -> GraphResult<String> {
+    write_graph_string(graph, id, Some(substitution), false, false)
+}
+
+/// the canonical form of [`transform_substituted_graph_into_string`]
+#[instrument]
+pub fn transform_substituted_graph_into_canonical_string(
+    graph: &Graph,
+    id: &GraphKey,
+    substitution: &Substitution,
+) -> GraphResult<String> {
+    write_graph_string(graph, id, Some(substitution), true, false)
+}
+
+#[instrument]
+fn write_graph_string(
+    graph: &Graph,
+    id: &GraphKey,
+    substitution: Option<&Substitution>,
+    canonical: bool,
+    keyed: bool,
+) -> GraphResult<String> {
+    let mut atoms = Vec::new();
+    let mut subgraphs = Vec::new();
+
+    for atom in graph.atoms_of(id)? {
+        match substitution.and_then(|v| v.get(&**atom)) {
+            Some(substitute) => {
+                let root = substitute.root_id();
+
+                atoms.extend(
+                    substitute
+                        .atoms_of(root)?
+                        .iter()
+                        .map(|e| escape_atom(&e.to_string())),
+                );
+
+                for e in substitute.subgraphs_of(root)? {
+                    subgraphs.push(write_graph_string(substitute, e, None, canonical, keyed)?);
+                }
+            }
+            None => atoms.push(escape_atom(&atom.to_string())),
+        }
+    }
+
+    for e in graph.subgraphs_of(id)? {
+        subgraphs.push(write_graph_string(
+            graph,
+            e,
+            substitution,
+            canonical,
+            keyed,
+        )?);
+    }
+
+    if canonical {
+        atoms.sort();
+        subgraphs.sort();
+    }
+
+    // a substitution can bring in atoms the area already has
+    if substitution.is_some() {
+        atoms = atoms.into_iter().unique().collect();
+    }
+
+    Ok(format!(
+        "{}[{}]",
+        if keyed {
+            format!("{}:", id)
+        } else {
+            String::new()
+        },
+        Iterator::chain(atoms.into_iter(), subgraphs).join(",")
+    ))
 }
 
 #[instrument]
@@ -351,53 +758,73 @@ pub fn parse_graph_string_into_actions(
     substr: &str,
     root: GraphKey,
 ) -> GraphResult<LinkedList<Action>> {
-    if !(substr.starts_with('[') && substr.ends_with(']')) {
+    // anything before the first `[` has to be a key, which is checked below
+    if !(substr.contains('[') && substr.ends_with(']')) {
         Err(GraphError::ParseError)
     } else {
         let mut actions = LinkedList::new();
 
-        let mut levels = LinkedList::new();
+        // each open level remembers the atoms it already has, since areas can't hold duplicates
+        let mut levels: LinkedList<(GraphTarget, HashSet<String>)> = LinkedList::new();
+        let mut closed = false;
         let mut counter = 0;
         let mut curr_atom = String::new();
 
         for c in substr.chars() {
             match c {
+                _ if closed => Err(GraphError::ParseError)?,
                 '[' => {
-                    if curr_atom.is_empty() {
-                        levels.push_front(if levels.is_empty() {
-                            GraphTarget::Exists(root)
+                    // subgraphs can be given an id, as in `abc:[A]`
+                    let key = match curr_atom.strip_suffix(':') {
+                        Some(key) => {
+                            Some(GraphKey::try_from_str(key).map_err(|_| GraphError::ParseError)?)
+                        }
+                        None if curr_atom.is_empty() => None,
+                        None => Err(GraphError::ParseError)?,
+                    };
+                    curr_atom.clear();
+
+                    {
+                        let level = if levels.is_empty() {
+                            (
+                                GraphTarget::Exists(root),
+                                graph
+                                    .atoms_of(&root)?
+                                    .iter()
+                                    .map(|a| a.to_string())
+                                    .collect(),
+                            )
                         } else {
-                            counter += 1;
-
-                            let ans = GraphTarget::Future(counter);
+                            let ans = match key {
+                                Some(key) => GraphTarget::Exists(key),
+                                None => {
+                                    counter += 1;
+                                    GraphTarget::Future(counter)
+                                }
+                            };
 
                             actions.push_back(Action::AddSubgraph {
-                                target: levels.front().ok_or(GraphError::ParseError).cloned()?,
+                                target: levels.front().ok_or(GraphError::ParseError)?.0.clone(),
                                 new_subgraph: ans.clone(),
                             });
 
-                            ans
-                        });
-                    } else {
-                        Err(GraphError::ParseError)?
+                            (ans, HashSet::new())
+                        };
+
+                        levels.push_front(level);
                     }
                 }
                 ']' => {
                     if !curr_atom.is_empty() {
-                        actions.push_back(Action::AddAtom {
-                            target: levels.front().ok_or(GraphError::ParseError).cloned()?,
-                            atom: mem::take(&mut curr_atom).into(),
-                        });
+                        push_parsed_atom(&mut actions, &mut levels, mem::take(&mut curr_atom))?;
                     }
 
-                    levels.pop_front();
+                    levels.pop_front().ok_or(GraphError::ParseError)?;
+                    closed = levels.is_empty();
                 }
                 ',' => {
                     if !curr_atom.is_empty() {
-                        actions.push_back(Action::AddAtom {
-                            target: levels.front().ok_or(GraphError::ParseError).cloned()?,
-                            atom: mem::take(&mut curr_atom).into(),
-                        });
+                        push_parsed_atom(&mut actions, &mut levels, mem::take(&mut curr_atom))?;
                     }
                 }
                 c if !c.is_whitespace() => {
@@ -416,3 +843,33 @@ pub fn parse_graph_string_into_actions(
         }
     }
 }
+
+/// writes an atom name the way graph strings have it, with `%`, whitespace and `[ ] , :` as
+/// `%XX` escapes, so that reading the string back gives the same name
+pub fn escape_atom(name: &str) -> String {
+    escape_field(name, "[],:")
+}
+
+/// undoes [`escape_atom`]
+pub fn unescape_atom(s: &str) -> GraphResult<String> {
+    unescape_field(s).map_err(|_| GraphError::ParseError)
+}
+
+#[instrument]
+fn push_parsed_atom(
+    actions: &mut LinkedList<Action>,
+    levels: &mut LinkedList<(GraphTarget, HashSet<String>)>,
+    atom: String,
+) -> GraphResult<()> {
+    let (target, seen) = levels.front_mut().ok_or(GraphError::ParseError)?;
+    let atom = unescape_atom(&atom)?;
+
+    if seen.insert(atom.clone()) {
+        actions.push_back(Action::AddAtom {
+            target: target.clone(),
+            atom: atom.into(),
+        });
+    }
+
+    Ok(())
+}
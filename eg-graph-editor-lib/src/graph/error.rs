@@ -1,3 +1,5 @@
+use itertools::Itertools;
+use std::fmt::Display;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -12,8 +14,134 @@ pub enum GraphError {
     #[error("Graph already has a subgraph with id {0}")]
     DuplicateIdError(String),
 
+    #[error("Atom {0} does not exist in graph {1}")]
+    InvalidAtomTargetError(String, String),
+
+    #[error("Atom {0} already exists in graph {1}")]
+    DuplicateAtomError(String, String),
+
+    #[error("Atom {0} does not exist in the graph")]
+    UnknownAtomError(String),
+
+    #[error("Cannot move graph {0} into itself or one of its subgraphs")]
+    CyclicMoveError(String),
+
     #[error("Could not parse graph from string")]
     ParseError,
+
+    #[error("Graph has {0} atoms, but at most {1} can be checked")]
+    TooManyAtomsError(usize, usize),
+
+    #[error("Graph is in an inconsistent state:\n{}", .0.iter().map(|v| format!("  - {}", v)).join("\n"))]
+    InvariantViolationError(Vec<InvariantViolation>),
+}
+
+/// a single inconsistency found by [`super::Graph::check_invariants`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvariantViolation {
+    /// the root graph has a parent registered
+    RootHasParent { parent: String },
+
+    /// a non-root graph has no parent registered
+    MissingParent { id: String },
+
+    /// a graph's parent does not exist
+    ParentDoesNotExist { id: String, parent: String },
+
+    /// a graph's parent does not list it as a child
+    NotAChildOfParent { id: String, parent: String },
+
+    /// a graph lists a child that does not exist
+    ChildDoesNotExist { id: String, child: String },
+
+    /// a graph lists a child whose registered parent is a different graph
+    ChildHasOtherParent {
+        id: String,
+        child: String,
+        parent: Option<String>,
+    },
+
+    /// a parent is registered for a graph that does not exist
+    DanglingParentEntry { id: String },
+
+    /// a graph's stored level is not its depth from the root
+    WrongLevel {
+        id: String,
+        level: usize,
+        depth: usize,
+    },
+
+    /// a graph cannot be reached by walking down from the root
+    Unreachable { id: String },
+
+    /// the recorded count of an atom does not match how often it occurs
+    WrongAtomCount {
+        atom: String,
+        recorded: usize,
+        actual: usize,
+    },
+}
+
+impl Display for InvariantViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvariantViolation::RootHasParent { parent } => {
+                write!(f, "root graph has parent {}", parent)
+            }
+            InvariantViolation::MissingParent { id } => {
+                write!(f, "graph {} has no parent", id)
+            }
+            InvariantViolation::ParentDoesNotExist { id, parent } => {
+                write!(
+                    f,
+                    "graph {} has parent {}, which does not exist",
+                    id, parent
+                )
+            }
+            InvariantViolation::NotAChildOfParent { id, parent } => {
+                write!(
+                    f,
+                    "graph {} is not registered as a child of its parent {}",
+                    id, parent
+                )
+            }
+            InvariantViolation::ChildDoesNotExist { id, child } => {
+                write!(f, "graph {} has child {}, which does not exist", id, child)
+            }
+            InvariantViolation::ChildHasOtherParent { id, child, parent } => write!(
+                f,
+                "graph {} has child {}, but the child's parent is {}",
+                id,
+                child,
+                parent.as_deref().unwrap_or("not set")
+            ),
+            InvariantViolation::DanglingParentEntry { id } => {
+                write!(
+                    f,
+                    "a parent is registered for graph {}, which does not exist",
+                    id
+                )
+            }
+            InvariantViolation::WrongLevel { id, level, depth } => write!(
+                f,
+                "graph {} is on level {}, but has depth {}",
+                id, level, depth
+            ),
+            InvariantViolation::Unreachable { id } => {
+                write!(f, "graph {} is not reachable from the root", id)
+            }
+            InvariantViolation::WrongAtomCount {
+                atom,
+                recorded,
+                actual,
+            } => write!(
+                f,
+                "atom {} is recorded {} times, but occurs {} times",
+                atom, recorded, actual
+            ),
+        }
+    }
 }
 
 pub type GraphResult<T> = Result<T, GraphError>;
@@ -0,0 +1,102 @@
+use super::{
+    error::{GraphError, GraphResult},
+    Graph, GraphKey,
+};
+use itertools::Itertools;
+use std::collections::BTreeSet;
+use tracing::instrument;
+
+/// the most atoms [`check`] will enumerate assignments for
+pub const MAX_ATOMS: usize = 20;
+
+/// what a truth table says about a graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    /// whether the graph holds under every assignment
+    pub valid: bool,
+
+    /// the atoms made true by an assignment under which the graph holds, if there is one
+    pub model: Option<BTreeSet<String>>,
+
+    /// the atoms made true by an assignment under which the graph fails, if there is one
+    pub counterexample: Option<BTreeSet<String>>,
+}
+
+impl Check {
+    pub fn satisfiable(&self) -> bool {
+        self.model.is_some()
+    }
+}
+
+/// whether the area `id` holds when exactly the atoms in `truths` are true: every atom in
+/// it is true and every cut in it fails
+#[instrument(skip(graph))]
+pub fn evaluate(graph: &Graph, id: &GraphKey, truths: &BTreeSet<String>) -> GraphResult<bool> {
+    for atom in graph.atoms_of(id)? {
+        if !truths.contains(&atom.to_string()) {
+            return Ok(false);
+        }
+    }
+
+    for k in graph.subgraphs_of(id)? {
+        if evaluate(graph, k, truths)? {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// every atom that occurs in the area `id` or below it
+#[instrument(skip(graph))]
+pub fn atoms_below(graph: &Graph, id: &GraphKey) -> GraphResult<BTreeSet<String>> {
+    let mut ans = graph
+        .atoms_of(id)?
+        .iter()
+        .map(|a| a.to_string())
+        .collect::<BTreeSet<_>>();
+
+    for k in graph.subgraphs_of(id)? {
+        ans.extend(atoms_below(graph, k)?);
+    }
+
+    Ok(ans)
+}
+
+/// evaluates the area `id` under every assignment of its atoms
+#[instrument(skip(graph))]
+pub fn check(graph: &Graph, id: &GraphKey) -> GraphResult<Check> {
+    let atoms = atoms_below(graph, id)?.into_iter().collect_vec();
+
+    if atoms.len() > MAX_ATOMS {
+        Err(GraphError::TooManyAtomsError(atoms.len(), MAX_ATOMS))?
+    }
+
+    let mut model = None;
+    let mut counterexample = None;
+
+    for assignment in 0..1u64 << atoms.len() {
+        let truths = atoms
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| assignment & (1 << i) != 0)
+            .map(|(_, a)| a.clone())
+            .collect::<BTreeSet<_>>();
+
+        match evaluate(graph, id, &truths)? {
+            true if model.is_none() => model = Some(truths),
+            false if counterexample.is_none() => counterexample = Some(truths),
+            _ => {}
+        }
+
+        if model.is_some() && counterexample.is_some() {
+            break;
+        }
+    }
+
+    Ok(Check {
+        valid: counterexample.is_none(),
+        model,
+        counterexample,
+    })
+}
@@ -0,0 +1,391 @@
+use super::{
+    error::{GraphError, GraphResult},
+    escape_atom, transform_graph_into_canonical_string, Graph, GraphKey,
+};
+use crate::proof::action::error::ActionResult;
+use itertools::Itertools;
+use std::{fmt::Display, iter::Peekable, str::Chars, str::FromStr};
+use tracing::instrument;
+
+/// the ways a graph can be written down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    /// `[A,[B]]`, as read by [`Graph::try_from`]
+    Bracket,
+
+    /// `(A,(B))`
+    Parenthesis,
+
+    /// the alpha part of the Existential Graph Interchange Format, as in `(A) ~[ (B) ]`
+    Egif,
+
+    /// a propositional formula, as in `A & !B`, with `!`, `&`, `|`, `->`, `true` and `false`
+    Formula,
+}
+
+impl Display for Syntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Syntax::Bracket => "bracket",
+                Syntax::Parenthesis => "parenthesis",
+                Syntax::Egif => "egif",
+                Syntax::Formula => "formula",
+            }
+        )
+    }
+}
+
+impl FromStr for Syntax {
+    type Err = GraphError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bracket" => Ok(Syntax::Bracket),
+            "parenthesis" | "paren" => Ok(Syntax::Parenthesis),
+            "egif" => Ok(Syntax::Egif),
+            "formula" => Ok(Syntax::Formula),
+            _ => Err(GraphError::ParseError),
+        }
+    }
+}
+
+/// writes the area `id` of `graph` in `syntax`, with atoms sorted so the output is always the same
+#[instrument]
+pub fn write_graph(graph: &Graph, id: &GraphKey, syntax: Syntax) -> GraphResult<String> {
+    Ok(match syntax {
+        Syntax::Bracket => transform_graph_into_canonical_string(graph, id)?,
+        Syntax::Parenthesis => transform_graph_into_canonical_string(graph, id)?
+            .replace('[', "(")
+            .replace(']', ")"),
+        Syntax::Egif => write_egif(graph, id)?,
+        Syntax::Formula => Formula::of_area(graph, id)?.to_string(),
+    })
+}
+
+/// reads a graph written in `syntax`
+#[instrument]
+pub fn read_graph(s: &str, syntax: Syntax) -> ActionResult<Graph> {
+    let brackets = match syntax {
+        Syntax::Bracket => s.to_string(),
+        Syntax::Parenthesis => {
+            if s.contains(['[', ']']) {
+                Err(GraphError::ParseError)?
+            }
+
+            s.replace('(', "[").replace(')', "]")
+        }
+        Syntax::Egif => format!("[{}]", read_egif(s)?.join(",")),
+        Syntax::Formula => format!("[{}]", Formula::parse(s)?.items().join(",")),
+    };
+
+    Graph::try_from(brackets.trim())
+}
+
+#[instrument(skip(graph))]
+fn write_egif(graph: &Graph, id: &GraphKey) -> GraphResult<String> {
+    let atoms = graph
+        .atoms_of(id)?
+        .iter()
+        .map(|a| format!("({})", a))
+        .sorted();
+
+    let mut cuts = Vec::new();
+    for k in graph.subgraphs_of(id)? {
+        let contents = write_egif(graph, k)?;
+
+        cuts.push(if contents.is_empty() {
+            "~[ ]".to_string()
+        } else {
+            format!("~[ {} ]", contents)
+        });
+    }
+
+    Ok(atoms.chain(cuts).join(" "))
+}
+
+/// reads EGIF into the items of an area in bracket syntax
+fn read_egif(s: &str) -> GraphResult<Vec<String>> {
+    let mut levels = vec![Vec::new()];
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == ')' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                let name = name.trim();
+
+                if !closed || name.is_empty() || name.contains(|c: char| "[](),~:".contains(c)) {
+                    Err(GraphError::ParseError)?
+                }
+
+                levels
+                    .last_mut()
+                    .ok_or(GraphError::ParseError)?
+                    .push(escape_atom(name));
+            }
+            '~' => {
+                skip_whitespace(&mut chars);
+
+                if chars.next() != Some('[') {
+                    Err(GraphError::ParseError)?
+                }
+
+                levels.push(Vec::new());
+            }
+            ']' if levels.len() > 1 => {
+                let items = levels.pop().ok_or(GraphError::ParseError)?;
+
+                levels
+                    .last_mut()
+                    .ok_or(GraphError::ParseError)?
+                    .push(format!("[{}]", items.join(",")));
+            }
+            c if c.is_whitespace() => {}
+            _ => Err(GraphError::ParseError)?,
+        }
+    }
+
+    match &levels[..] {
+        [items] => Ok(items.clone()),
+        _ => Err(GraphError::ParseError),
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+/// a propositional formula
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Formula {
+    True,
+    False,
+    Atom(String),
+    Not(Box<Formula>),
+    And(Vec<Formula>),
+    Or(Vec<Formula>),
+    Implies(Box<Formula>, Box<Formula>),
+}
+
+impl Formula {
+    /// what the contents of an area say together
+    fn of_area(graph: &Graph, id: &GraphKey) -> GraphResult<Self> {
+        let mut items = graph
+            .atoms_of(id)?
+            .iter()
+            .map(|a| a.to_string())
+            .sorted()
+            .map(Formula::Atom)
+            .collect_vec();
+
+        for k in graph.subgraphs_of(id)? {
+            items.push(Formula::of_cut(graph, k)?);
+        }
+
+        Ok(Formula::and(items))
+    }
+
+    /// what a cut says, recognising the shapes of disjunctions and implications
+    fn of_cut(graph: &Graph, id: &GraphKey) -> GraphResult<Self> {
+        let atoms = graph.atoms_of(id)?;
+        let cuts = graph.subgraphs_of(id)?;
+
+        Ok(match (atoms.len(), cuts.len()) {
+            (0, 0) => Formula::False,
+
+            // [[A],[B]] is A | B
+            (0, n) if n > 1 => Formula::Or(
+                cuts.iter()
+                    .map(|k| Formula::of_area(graph, k))
+                    .collect::<GraphResult<_>>()?,
+            ),
+
+            // [A,[B]] is A -> B
+            (n, 1) if n > 0 => {
+                let consequent = cuts.front().ok_or(GraphError::ParseError)?;
+
+                Formula::Implies(
+                    Box::new(Formula::and(
+                        atoms
+                            .iter()
+                            .map(|a| a.to_string())
+                            .sorted()
+                            .map(Formula::Atom)
+                            .collect(),
+                    )),
+                    Box::new(Formula::of_area(graph, consequent)?),
+                )
+            }
+
+            _ => Formula::Not(Box::new(Formula::of_area(graph, id)?)),
+        })
+    }
+
+    fn and(mut items: Vec<Formula>) -> Self {
+        match items.len() {
+            0 => Formula::True,
+            1 => items.remove(0),
+            _ => Formula::And(items),
+        }
+    }
+
+    /// the formula as the items of an area in bracket syntax
+    fn items(&self) -> Vec<String> {
+        let cut = |items: Vec<String>| format!("[{}]", items.join(","));
+
+        match self {
+            Formula::True => vec![],
+            Formula::False => vec![cut(vec![])],
+            Formula::Atom(a) => vec![escape_atom(a)],
+            Formula::Not(v) => vec![cut(v.items())],
+            Formula::And(v) => v.iter().flat_map(|v| v.items()).collect(),
+            Formula::Or(v) => vec![cut(v.iter().map(|v| cut(v.items())).collect())],
+            Formula::Implies(a, b) => {
+                let mut items = a.items();
+                items.push(cut(b.items()));
+                vec![cut(items)]
+            }
+        }
+    }
+
+    /// how tightly the formula binds, for deciding on parentheses
+    fn precedence(&self) -> u8 {
+        match self {
+            Formula::Implies(..) => 1,
+            Formula::Or(_) => 2,
+            Formula::And(_) => 3,
+            _ => 4,
+        }
+    }
+
+    fn parse(s: &str) -> GraphResult<Self> {
+        let mut parser = FormulaParser {
+            chars: s.chars().peekable(),
+        };
+
+        let ans = parser.implication()?;
+        skip_whitespace(&mut parser.chars);
+
+        match parser.chars.peek() {
+            None => Ok(ans),
+            Some(_) => Err(GraphError::ParseError),
+        }
+    }
+}
+
+/// parentheses only go where precedence needs them
+impl Display for Formula {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let child = |v: &Formula, min: u8| {
+            if v.precedence() < min {
+                format!("({})", v)
+            } else {
+                v.to_string()
+            }
+        };
+
+        match self {
+            Formula::True => write!(f, "true"),
+            Formula::False => write!(f, "false"),
+            Formula::Atom(a) => write!(f, "{}", a),
+            Formula::Not(v) => write!(f, "!{}", child(v, 4)),
+            Formula::And(v) => write!(f, "{}", v.iter().map(|v| child(v, 4)).join(" & ")),
+            Formula::Or(v) => write!(f, "{}", v.iter().map(|v| child(v, 3)).join(" | ")),
+            Formula::Implies(a, b) => write!(f, "{} -> {}", child(a, 2), child(b, 1)),
+        }
+    }
+}
+
+/// a recursive descent parser, from the loosest binding operator to the tightest
+struct FormulaParser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl FormulaParser<'_> {
+    /// whether `token` comes next, consuming it if so
+    fn eat(&mut self, token: &str) -> bool {
+        skip_whitespace(&mut self.chars);
+
+        let mut ahead = self.chars.clone();
+        if token.chars().all(|c| ahead.next() == Some(c)) {
+            self.chars = ahead;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn implication(&mut self) -> GraphResult<Formula> {
+        let antecedent = self.disjunction()?;
+
+        if self.eat("->") || self.eat("→") {
+            Ok(Formula::Implies(
+                Box::new(antecedent),
+                Box::new(self.implication()?),
+            ))
+        } else {
+            Ok(antecedent)
+        }
+    }
+
+    fn disjunction(&mut self) -> GraphResult<Formula> {
+        let mut items = vec![self.conjunction()?];
+
+        while self.eat("|") || self.eat("∨") {
+            items.push(self.conjunction()?);
+        }
+
+        Ok(match items.len() {
+            1 => items.remove(0),
+            _ => Formula::Or(items),
+        })
+    }
+
+    fn conjunction(&mut self) -> GraphResult<Formula> {
+        let mut items = vec![self.unary()?];
+
+        while self.eat("&") || self.eat("∧") {
+            items.push(self.unary()?);
+        }
+
+        Ok(Formula::and(items))
+    }
+
+    fn unary(&mut self) -> GraphResult<Formula> {
+        if self.eat("!") || self.eat("~") || self.eat("¬") {
+            return Ok(Formula::Not(Box::new(self.unary()?)));
+        }
+
+        if self.eat("(") {
+            let ans = self.implication()?;
+
+            return match self.eat(")") {
+                true => Ok(ans),
+                false => Err(GraphError::ParseError),
+            };
+        }
+
+        let mut name = String::new();
+        while let Some(c) = self.chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+            name.push(c);
+        }
+
+        match name.as_str() {
+            "" => Err(GraphError::ParseError),
+            "true" => Ok(Formula::True),
+            "false" => Ok(Formula::False),
+            _ => Ok(Formula::Atom(name)),
+        }
+    }
+}
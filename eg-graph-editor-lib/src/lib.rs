@@ -3,6 +3,7 @@
 pub mod atom;
 pub mod graph;
 pub mod proof;
+pub mod render;
 
 #[cfg(test)]
 mod test;
@@ -1,5 +1,168 @@
 pub mod action;
+pub mod derived_rule;
 pub mod error;
+pub mod hint;
 pub mod inference_rule;
+pub mod lemma;
+pub mod minimize;
+pub mod problem;
+pub mod recognize;
+pub mod rule;
+pub mod rule_set;
+pub mod search;
 
-pub struct Proof {}
+use self::{
+    action::Action,
+    error::{ProofError, ProofResult},
+    problem::Problem,
+    rule::{Rule, RuleRegistry},
+    rule_set::RuleSet,
+};
+use crate::graph::{transform_graph_into_keyed_string, Graph};
+use std::{fmt::Display, str::FromStr, sync::Arc};
+use tracing::instrument;
+
+/// a list of steps leading from a premise to whatever graph they produce
+///
+/// a step can be any [`Rule`], not only the built in ones
+#[derive(Debug, Clone)]
+pub struct Proof {
+    premise: Graph,
+    steps: Vec<Arc<dyn Rule>>,
+    rule_set: RuleSet,
+}
+
+impl Proof {
+    #[instrument]
+    pub fn new(premise: Graph, steps: Vec<Arc<dyn Rule>>) -> Self {
+        Self {
+            premise,
+            steps,
+            rule_set: RuleSet::default(),
+        }
+    }
+
+    /// the same proof, checked against `rule_set` instead of the default
+    #[instrument]
+    pub fn with_rule_set(mut self, rule_set: RuleSet) -> Self {
+        self.rule_set = rule_set;
+        self
+    }
+
+    pub fn premise(&self) -> &Graph {
+        &self.premise
+    }
+
+    pub fn steps(&self) -> &[Arc<dyn Rule>] {
+        &self.steps
+    }
+
+    pub fn rule_set(&self) -> &RuleSet {
+        &self.rule_set
+    }
+
+    /// replays every step on a copy of the premise
+    #[instrument]
+    pub fn conclusion(&self) -> ProofResult<Graph> {
+        let mut graph = self.premise.clone();
+
+        for step in &self.steps {
+            Action::apply_actions(
+                step.gen_actions_with_rules(&graph, &self.rule_set)?,
+                &mut graph,
+            )?;
+        }
+
+        Ok(graph)
+    }
+
+    /// whether the steps so far reach the goal of `problem`
+    #[instrument]
+    pub fn reaches(&self, problem: &Problem) -> ProofResult<bool> {
+        Ok(problem.is_reached_by(&self.conclusion()?)?)
+    }
+
+    /// reads a proof, as written by its [`Display`] implementation, looking the steps up in
+    /// `registry`; the key state can be left out of the premise line
+    #[instrument(skip(registry))]
+    pub fn parse(s: &str, registry: &RuleRegistry) -> ProofResult<Self> {
+        let mut premise = None;
+        let mut rule_set = None;
+        let mut steps = Vec::new();
+
+        for line in s
+            .lines()
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty() && !v.starts_with('#'))
+        {
+            let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let rest = rest.trim();
+
+            match word {
+                "premise" if premise.is_none() => {
+                    premise = Some(
+                        match rest.split_once(' ').map(|(k, v)| (k.parse::<u64>(), v)) {
+                            Some((Ok(key_state), graph)) => {
+                                Graph::try_from(graph.trim())?.with_key_state(key_state)
+                            }
+                            _ => Graph::try_from(rest)?,
+                        },
+                    )
+                }
+                "rules" if rule_set.is_none() && steps.is_empty() => {
+                    rule_set = Some(RuleSet::from_str(rest)?)
+                }
+                "step" if premise.is_some() => steps.push(Arc::from(registry.parse(rest)?)),
+                _ => Err(ProofError::ProofParseError(format!("unexpected {}", line)))?,
+            }
+        }
+
+        let premise =
+            premise.ok_or_else(|| ProofError::ProofParseError("missing premise".to_string()))?;
+
+        Ok(Proof::new(premise, steps).with_rule_set(rule_set.unwrap_or_default()))
+    }
+}
+
+/// writes the proof as
+///
+/// ```text
+/// premise KEY_STATE KEYED_GRAPH
+/// rules RULE_SET
+/// step RULE
+/// ...
+/// ```
+///
+/// with the rules line left out for the default rule set
+impl Display for Proof {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "premise {} {}",
+            self.premise.key_state(),
+            transform_graph_into_keyed_string(&self.premise, self.premise.root_id())
+                .map_err(|_| std::fmt::Error)?
+        )?;
+
+        if self.rule_set != RuleSet::default() {
+            writeln!(f, "rules {}", self.rule_set)?;
+        }
+
+        for step in &self.steps {
+            writeln!(f, "step {}", step.serialize())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Proof {
+    type Err = ProofError;
+
+    /// reads a proof, as written by its [`Display`] implementation, with the steps limited to
+    /// the rules [`RuleRegistry::new`] knows about
+    #[instrument]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, &RuleRegistry::new())
+    }
+}
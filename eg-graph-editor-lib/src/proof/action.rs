@@ -1,6 +1,6 @@
 pub mod error;
 
-use tracing::instrument;
+use tracing::{instrument, warn};
 
 use self::error::{ActionError, ActionResult};
 use crate::{
@@ -10,10 +10,12 @@ use crate::{
 use std::{
     borrow::Borrow,
     collections::{HashMap, LinkedList},
-    fmt::Debug,
+    fmt::{Debug, Display},
+    mem,
+    str::FromStr,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Action {
     AddAtom {
         target: GraphTarget,
@@ -43,6 +45,9 @@ pub enum GraphTarget {
 }
 
 impl Action {
+    /// applies every action in order, returning the actions that would undo them
+    ///
+    /// if any action fails, the ones already applied are undone before the error is returned
     #[instrument]
     pub fn apply_actions<S: IntoIterator<Item = Self> + Debug>(
         actions: S,
@@ -53,80 +58,252 @@ impl Action {
         let mut matched_future_targets = HashMap::new();
 
         for action in actions {
-            match action {
-                Action::AddAtom { target, atom } => {
-                    let t_id = resolve_target(&target, &matched_future_targets)?;
-                    graph.insert_atom(t_id, atom.clone())?;
-
-                    reversed_actions.push_front(Action::DeleteAtom { target, atom })
+            match action.apply(graph, &mut matched_future_targets) {
+                Ok(reversed) => reversed_actions.push_front(reversed),
+                Err(e) => {
+                    Self::roll_back(
+                        mem::take(&mut reversed_actions),
+                        graph,
+                        &mut matched_future_targets,
+                    );
+                    Err(e)?
                 }
+            }
+        }
 
-                Action::DeleteAtom { target, atom } => {
-                    let t_id = resolve_target(&target, &matched_future_targets)?;
-                    graph.remove_atom_from_subgraph(t_id, atom.borrow())?;
+        // a graph that breaks its invariants is rolled back like any other failed application
+        #[cfg(debug_assertions)]
+        if let Err(e) = graph.check_invariants() {
+            Self::roll_back(
+                mem::take(&mut reversed_actions),
+                graph,
+                &mut matched_future_targets,
+            );
+            Err(e)?
+        }
 
-                    reversed_actions.push_front(Action::AddAtom { target, atom })
-                }
+        Ok(reversed_actions)
+    }
 
-                Action::AddSubgraph {
-                    target,
-                    new_subgraph,
-                } => {
-                    let t_id = resolve_target(&target, &matched_future_targets)?;
-
-                    match new_subgraph {
-                        GraphTarget::Exists(new_id) => {
-                            if graph.level_of(&new_id).is_ok() {
-                                Err(ActionError::SubgraphIdAlreadyExists(new_id.to_string()))?
-                            } else {
-                                graph.insert_subgraph_with_id(new_id, t_id)?;
-                            }
-
-                            reversed_actions.push_front(Action::DeleteSubgraph {
-                                target: GraphTarget::Exists(new_id),
-                            })
-                        }
+    /// applies the reverses of the actions applied so far, newest first
+    #[instrument(skip(graph))]
+    fn roll_back(
+        reversed_actions: LinkedList<Action>,
+        graph: &mut Graph,
+        matched_future_targets: &mut HashMap<usize, GraphKey>,
+    ) {
+        for reversed in reversed_actions {
+            if let Err(e) = reversed.apply(graph, matched_future_targets) {
+                warn!("Could not roll back action: {}", e);
+            }
+        }
+    }
+
+    #[instrument]
+    fn apply(
+        self,
+        graph: &mut Graph,
+        matched_future_targets: &mut HashMap<usize, GraphKey>,
+    ) -> ActionResult<Action> {
+        match self {
+            Action::AddAtom { target, atom } => {
+                let t_id = *resolve_target(&target, matched_future_targets)?;
+                graph.insert_atom(&t_id, atom.clone())?;
+
+                Ok(Action::DeleteAtom {
+                    target: GraphTarget::Exists(t_id),
+                    atom,
+                })
+            }
 
-                        GraphTarget::Future(x) => {
-                            let new_id = graph.insert_subgraph(t_id)?;
+            Action::DeleteAtom { target, atom } => {
+                let t_id = *resolve_target(&target, matched_future_targets)?;
+                graph.remove_atom_from_subgraph(&t_id, atom.borrow())?;
 
-                            matched_future_targets.insert(x, new_id);
+                Ok(Action::AddAtom {
+                    target: GraphTarget::Exists(t_id),
+                    atom,
+                })
+            }
 
-                            reversed_actions.push_front(Action::DeleteSubgraph {
-                                target: GraphTarget::Exists(new_id),
-                            })
+            Action::AddSubgraph {
+                target,
+                new_subgraph,
+            } => {
+                let t_id = *resolve_target(&target, matched_future_targets)?;
+
+                match new_subgraph {
+                    GraphTarget::Exists(new_id) => {
+                        if graph.level_of(&new_id).is_ok() {
+                            Err(ActionError::SubgraphIdAlreadyExists(new_id.to_string()))?
+                        } else {
+                            graph.insert_subgraph_with_id(new_id, &t_id)?;
                         }
+
+                        Ok(Action::DeleteSubgraph {
+                            target: GraphTarget::Exists(new_id),
+                        })
                     }
-                }
 
-                Action::DeleteSubgraph { target } => {
-                    let t_id = resolve_target(&target, &matched_future_targets)?;
-                    let p_id = *graph.parent_of(t_id)?;
+                    GraphTarget::Future(x) => {
+                        let new_id = graph.insert_subgraph(&t_id)?;
 
-                    graph.remove_subgraph(t_id, false)?;
+                        matched_future_targets.insert(x, new_id);
 
-                    reversed_actions.push_front(Action::AddSubgraph {
-                        target: GraphTarget::Exists(p_id),
-                        new_subgraph: target,
-                    })
+                        Ok(Action::DeleteSubgraph {
+                            target: GraphTarget::Exists(new_id),
+                        })
+                    }
                 }
+            }
 
-                Action::MoveSubgraph { target, dest } => {
-                    let t_id = resolve_target(&target, &matched_future_targets)?;
-                    let d_id = resolve_target(&dest, &matched_future_targets)?;
-                    let s_id = *graph.parent_of(t_id)?;
+            Action::DeleteSubgraph { target } => {
+                let t_id = *resolve_target(&target, matched_future_targets)?;
+                let p_id = *graph.parent_of(&t_id)?;
 
-                    graph.move_subgraph(t_id, d_id)?;
+                graph.remove_subgraph(&t_id, false)?;
 
-                    reversed_actions.push_front(Action::MoveSubgraph {
-                        target: GraphTarget::Exists(*t_id),
-                        dest: GraphTarget::Exists(s_id),
-                    })
-                }
+                Ok(Action::AddSubgraph {
+                    target: GraphTarget::Exists(p_id),
+                    new_subgraph: GraphTarget::Exists(t_id),
+                })
+            }
+
+            Action::MoveSubgraph { target, dest } => {
+                let t_id = resolve_target(&target, matched_future_targets)?;
+                let d_id = resolve_target(&dest, matched_future_targets)?;
+                let s_id = *graph.parent_of(t_id)?;
+
+                graph.move_subgraph(t_id, d_id)?;
+
+                Ok(Action::MoveSubgraph {
+                    target: GraphTarget::Exists(*t_id),
+                    dest: GraphTarget::Exists(s_id),
+                })
             }
         }
+    }
+}
 
-        Ok(reversed_actions)
+impl Action {
+    /// renumbers every future graph by `offset`, so that lists of actions generated separately
+    /// can be applied together without their future graphs clashing
+    #[instrument]
+    pub fn shift_future_targets(self, offset: usize) -> Self {
+        let shift = |target: GraphTarget| match target {
+            GraphTarget::Future(x) => GraphTarget::Future(x + offset),
+            x => x,
+        };
+
+        match self {
+            Action::AddAtom { target, atom } => Action::AddAtom {
+                target: shift(target),
+                atom,
+            },
+            Action::DeleteAtom { target, atom } => Action::DeleteAtom {
+                target: shift(target),
+                atom,
+            },
+            Action::AddSubgraph {
+                target,
+                new_subgraph,
+            } => Action::AddSubgraph {
+                target: shift(target),
+                new_subgraph: shift(new_subgraph),
+            },
+            Action::DeleteSubgraph { target } => Action::DeleteSubgraph {
+                target: shift(target),
+            },
+            Action::MoveSubgraph { target, dest } => Action::MoveSubgraph {
+                target: shift(target),
+                dest: shift(dest),
+            },
+        }
+    }
+}
+
+/// writes the action on one line, as in `add-atom abc A` or `add-cut abc #0`, with future
+/// graphs written as `#N`
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::AddAtom { target, atom } => write!(f, "add-atom {} {}", target, atom),
+            Action::DeleteAtom { target, atom } => write!(f, "delete-atom {} {}", target, atom),
+            Action::AddSubgraph {
+                target,
+                new_subgraph,
+            } => write!(f, "add-cut {} {}", target, new_subgraph),
+            Action::DeleteSubgraph { target } => write!(f, "delete-cut {}", target),
+            Action::MoveSubgraph { target, dest } => write!(f, "move-cut {} {}", target, dest),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = ActionError;
+
+    /// reads an action, as written by its [`Display`] implementation
+    #[instrument]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.trim().splitn(3, ' ');
+        let mut next = || {
+            words
+                .next()
+                .ok_or_else(|| ActionError::ParseError(s.to_string()))
+        };
+
+        let ans = match next()? {
+            "add-atom" => Action::AddAtom {
+                target: next()?.parse()?,
+                atom: next()?.into(),
+            },
+            "delete-atom" => Action::DeleteAtom {
+                target: next()?.parse()?,
+                atom: next()?.into(),
+            },
+            "add-cut" => Action::AddSubgraph {
+                target: next()?.parse()?,
+                new_subgraph: next()?.parse()?,
+            },
+            "delete-cut" => Action::DeleteSubgraph {
+                target: next()?.parse()?,
+            },
+            "move-cut" => Action::MoveSubgraph {
+                target: next()?.parse()?,
+                dest: next()?.parse()?,
+            },
+            _ => Err(ActionError::ParseError(s.to_string()))?,
+        };
+
+        match words.next() {
+            None => Ok(ans),
+            Some(_) => Err(ActionError::ParseError(s.to_string())),
+        }
+    }
+}
+
+impl Display for GraphTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphTarget::Exists(x) => write!(f, "{}", x),
+            GraphTarget::Future(x) => write!(f, "#{}", x),
+        }
+    }
+}
+
+impl FromStr for GraphTarget {
+    type Err = ActionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('#') {
+            Some(x) => Ok(GraphTarget::Future(
+                x.parse()
+                    .map_err(|_| ActionError::ParseError(s.to_string()))?,
+            )),
+            None => Ok(GraphTarget::Exists(
+                GraphKey::try_from_str(s).map_err(|_| ActionError::ParseError(s.to_string()))?,
+            )),
+        }
     }
 }
 
@@ -10,6 +10,9 @@ pub enum ActionError {
     #[error("Cannot make a new subgraph with id \"{0}\" when it already exists")]
     SubgraphIdAlreadyExists(String),
 
+    #[error("Could not parse action: {0}")]
+    ParseError(String),
+
     #[error("Error operating on graph: {0}")]
     GraphError(#[from] GraphError),
 }
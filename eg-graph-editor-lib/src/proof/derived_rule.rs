@@ -0,0 +1,573 @@
+use super::{
+    action::Action,
+    error::{Element, Parity, ProofError, ProofResult},
+    inference_rule::{DoubleCutDraw, DoubleCutErase, Erasure, InferenceRule, Iteration},
+    rule::{escape_field, unescape_field, Rule, RuleRegistry},
+    rule_set::RuleSet,
+};
+use crate::{
+    atom::Atom,
+    graph::{
+        error::GraphResult, escape_atom, transform_graph_into_canonical_string, Graph, GraphKey,
+    },
+};
+use itertools::Itertools;
+use std::{
+    collections::{HashMap, LinkedList},
+    fmt::Display,
+    str::FromStr,
+    sync::Arc,
+};
+use tracing::instrument;
+
+/// common inferences that take several primitive steps, applied as one
+///
+/// in every rule the last cut inside an implication is taken to be its consequent, so
+/// `[P, [Q]]` reads as "P implies Q"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DerivedRule {
+    /// from `P` and `[P, [Q]]` in the same area, derive `Q`
+    ModusPonens {
+        /// the `P` sitting next to the implication
+        premise: Element,
+
+        /// the `[P, [Q]]` cut
+        implication: GraphKey,
+    },
+
+    /// from `[Q]` and `[P, [Q]]` in the same area, derive `[P]`
+    ModusTollens {
+        /// the `[Q]` cut
+        negation: GraphKey,
+
+        /// the `[P, [Q]]` cut
+        implication: GraphKey,
+    },
+
+    /// from `[P]` and `[[P], [Q]]` in the same area, derive `Q`
+    DisjunctiveSyllogism {
+        /// the `[P]` cut
+        negation: GraphKey,
+
+        /// the `[[P], [Q]]` cut
+        disjunction: GraphKey,
+    },
+
+    /// turns `[P, [Q]]` into `[[Q], [[P]]]`
+    Contraposition {
+        /// the `[P, [Q]]` cut
+        implication: GraphKey,
+    },
+
+    /// from `[P, [Q]]` and `[Q, [R]]` in the same even area, turn the first into `[P, [R]]`
+    HypotheticalSyllogism {
+        /// the `[P, [Q]]` cut, which becomes `[P, [R]]`
+        first: GraphKey,
+
+        /// the `[Q, [R]]` cut
+        second: GraphKey,
+    },
+}
+
+impl DerivedRule {
+    pub const NAMES: [&'static str; 5] = [
+        "modus_ponens",
+        "modus_tollens",
+        "disjunctive_syllogism",
+        "contraposition",
+        "hypothetical_syllogism",
+    ];
+
+    /// adds every derived rule to `registry`
+    #[instrument]
+    pub fn register_all(registry: &mut RuleRegistry) {
+        for name in Self::NAMES {
+            registry.register(name, |s| {
+                Ok(Box::new(DerivedRule::from_str(s)?) as Box<dyn Rule>)
+            });
+        }
+    }
+}
+
+impl Rule for DerivedRule {
+    fn name(&self) -> &str {
+        match self {
+            DerivedRule::ModusPonens { .. } => "modus_ponens",
+            DerivedRule::ModusTollens { .. } => "modus_tollens",
+            DerivedRule::DisjunctiveSyllogism { .. } => "disjunctive_syllogism",
+            DerivedRule::Contraposition { .. } => "contraposition",
+            DerivedRule::HypotheticalSyllogism { .. } => "hypothetical_syllogism",
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            DerivedRule::ModusPonens {
+                premise,
+                implication,
+            } => format!("Modus ponens on {} using {}", implication, premise),
+            DerivedRule::ModusTollens {
+                negation,
+                implication,
+            } => format!("Modus tollens on {} using {}", implication, negation),
+            DerivedRule::DisjunctiveSyllogism {
+                negation,
+                disjunction,
+            } => format!(
+                "Disjunctive syllogism on {} using {}",
+                disjunction, negation
+            ),
+            DerivedRule::Contraposition { implication } => {
+                format!("Contraposition of {}", implication)
+            }
+            DerivedRule::HypotheticalSyllogism { first, second } => {
+                format!("Hypothetical syllogism chaining {} into {}", second, first)
+            }
+        }
+    }
+
+    fn gen_actions(&self, graph: &Graph) -> ProofResult<LinkedList<Action>> {
+        self.gen_actions_with_rules(graph, &RuleSet::default())
+    }
+
+    /// the actions of every step of the expansion, one after another, each of which `rules` has
+    /// to allow
+    fn gen_actions_with_rules(
+        &self,
+        graph: &Graph,
+        rules: &RuleSet,
+    ) -> ProofResult<LinkedList<Action>> {
+        rules.check_enabled(self.name())?;
+
+        let mut scratch = graph.clone();
+        let mut ans = LinkedList::new();
+        let mut offset = 0;
+
+        for step in self.expand(graph)? {
+            let actions = step.gen_actions_with_rules(&scratch, rules)?;
+
+            Action::apply_actions(actions.clone(), &mut scratch)?;
+
+            // future graphs are only unique within a single step
+            let step_len = actions.len();
+            ans.extend(actions.into_iter().map(|a| a.shift_future_targets(offset)));
+            offset += step_len + 1;
+        }
+
+        Ok(ans)
+    }
+
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    /// works out the steps on a copy of `graph`; since the copy generates the same ids as the
+    /// original, the steps can be replayed on `graph` as they are
+    fn expand(&self, graph: &Graph) -> ProofResult<Vec<InferenceRule>> {
+        let mut expansion = Expansion {
+            scratch: graph.clone(),
+            steps: Vec::new(),
+        };
+
+        match self {
+            DerivedRule::ModusPonens {
+                premise,
+                implication,
+            } => {
+                let area = *expansion.scratch.parent_of(implication)?;
+                let scratch = &expansion.scratch;
+
+                let copy = match premise {
+                    Element::Atom(a) => {
+                        if !scratch.atoms_of(&area)?.contains(a) {
+                            Err(ProofError::ElementNotInArea {
+                                area,
+                                element: premise.clone(),
+                            })?
+                        }
+
+                        scratch
+                            .atoms_of(implication)?
+                            .get(a)
+                            .map(|a| Element::Atom(a.clone()))
+                    }
+                    Element::Subgraph(k) => {
+                        if scratch.parent_of(k)? != &area {
+                            Err(ProofError::ElementNotInArea {
+                                area,
+                                element: premise.clone(),
+                            })?
+                        }
+
+                        let premise_str = transform_graph_into_canonical_string(scratch, k)?;
+
+                        find_copy(scratch, implication, &premise_str)?.map(Element::Subgraph)
+                    }
+                }
+                .ok_or_else(|| ProofError::ElementNotInArea {
+                    area: *implication,
+                    element: premise.clone(),
+                })?;
+
+                let other_atoms = scratch.atoms_of(implication)?.len()
+                    - matches!(copy, Element::Atom(_)) as usize;
+                let other_subgraphs = scratch.subgraphs_of(implication)?.len()
+                    - matches!(copy, Element::Subgraph(_)) as usize;
+
+                if other_atoms != 0 || other_subgraphs != 1 {
+                    Err(ProofError::WrongShape {
+                        target: *implication,
+                        expected: "[P, [Q]]",
+                    })?
+                }
+
+                let (parent_atoms, parent_subgraphs) = split_element(copy);
+
+                expansion.push(InferenceRule::Iteration(Iteration {
+                    backwards: true,
+                    parent: *implication,
+                    parent_atoms,
+                    parent_subgraphs,
+                    target: area,
+                }))?;
+                expansion.push(InferenceRule::DoubleCutErase(DoubleCutErase {
+                    target: *implication,
+                }))?;
+            }
+
+            DerivedRule::ModusTollens {
+                negation,
+                implication,
+            } => {
+                let area = *expansion.scratch.parent_of(implication)?;
+                let copy = find_negation_copy(&expansion.scratch, &area, negation, implication)?;
+
+                expansion.push(InferenceRule::Iteration(Iteration {
+                    backwards: true,
+                    parent: *implication,
+                    parent_atoms: vec![],
+                    parent_subgraphs: vec![copy],
+                    target: area,
+                }))?;
+            }
+
+            DerivedRule::DisjunctiveSyllogism {
+                negation,
+                disjunction,
+            } => {
+                let area = *expansion.scratch.parent_of(disjunction)?;
+
+                if !expansion.scratch.atoms_of(disjunction)?.is_empty()
+                    || expansion.scratch.subgraphs_of(disjunction)?.len() != 2
+                {
+                    Err(ProofError::WrongShape {
+                        target: *disjunction,
+                        expected: "[[P], [Q]]",
+                    })?
+                }
+
+                let copy = find_negation_copy(&expansion.scratch, &area, negation, disjunction)?;
+
+                expansion.push(InferenceRule::Iteration(Iteration {
+                    backwards: true,
+                    parent: *disjunction,
+                    parent_atoms: vec![],
+                    parent_subgraphs: vec![copy],
+                    target: area,
+                }))?;
+                expansion.push(InferenceRule::DoubleCutErase(DoubleCutErase {
+                    target: *disjunction,
+                }))?;
+            }
+
+            DerivedRule::Contraposition { implication } => {
+                let (atoms, subgraphs, _) = split_implication(&expansion.scratch, implication)?;
+
+                expansion.push(InferenceRule::DoubleCutDraw(DoubleCutDraw {
+                    target: *implication,
+                    target_atoms: atoms,
+                    target_subgraphs: subgraphs,
+                }))?;
+            }
+
+            DerivedRule::HypotheticalSyllogism { first, second } => {
+                let scratch = &expansion.scratch;
+                let area = *scratch.parent_of(first)?;
+
+                if scratch.parent_of(second)? != &area {
+                    Err(ProofError::ElementNotInArea {
+                        area,
+                        element: Element::Subgraph(*second),
+                    })?
+                }
+
+                let area_level = scratch.level_of(&area)?;
+                if Parity::of(area_level) != Parity::Even {
+                    Err(ProofError::WrongParity {
+                        target: area,
+                        expected: Parity::Even,
+                        level: area_level,
+                    })?
+                }
+
+                let (_, _, middle) = split_implication(scratch, first)?;
+                let (second_atoms, second_subgraphs, _) = split_implication(scratch, second)?;
+
+                if transform_graph_into_canonical_string(scratch, &middle)?
+                    != canonical_selection(scratch, &second_atoms, &second_subgraphs)?
+                {
+                    Err(ProofError::WrongShape {
+                        target: *second,
+                        expected: "[Q, [R]], where Q matches the consequent of the first",
+                    })?
+                }
+
+                let middle_atoms = scratch.atoms_of(&middle)?.iter().cloned().collect_vec();
+                let middle_subgraphs = scratch.subgraphs_of(&middle)?.iter().cloned().collect_vec();
+
+                // copy the second implication into the consequent of the first
+                expansion.push(InferenceRule::Iteration(Iteration {
+                    backwards: false,
+                    parent: area,
+                    parent_atoms: vec![],
+                    parent_subgraphs: vec![*second],
+                    target: middle,
+                }))?;
+
+                let copy = *expansion
+                    .scratch
+                    .subgraphs_of(&middle)?
+                    .iter()
+                    .find(|k| !middle_subgraphs.contains(k))
+                    .ok_or(ProofError::MissingInnerCut { target: middle })?;
+
+                // its antecedent is already there, so it can go
+                let (copy_atoms, copy_subgraphs, _) = split_implication(&expansion.scratch, &copy)?;
+                if !copy_atoms.is_empty() || !copy_subgraphs.is_empty() {
+                    expansion.push(InferenceRule::Iteration(Iteration {
+                        backwards: true,
+                        parent: copy,
+                        parent_atoms: copy_atoms,
+                        parent_subgraphs: copy_subgraphs,
+                        target: middle,
+                    }))?;
+                }
+
+                expansion.push(InferenceRule::DoubleCutErase(DoubleCutErase {
+                    target: copy,
+                }))?;
+
+                // and the old consequent is on an even level, so it can be erased
+                if !middle_atoms.is_empty() || !middle_subgraphs.is_empty() {
+                    expansion.push(InferenceRule::Erasure(Erasure {
+                        target_subgraphs: middle_subgraphs,
+                        target_atoms: middle_atoms.into_iter().map(|a| (middle, a)).collect(),
+                    }))?;
+                }
+            }
+        }
+
+        Ok(expansion.steps)
+    }
+}
+
+/// the primitive steps worked out so far, and the graph they lead to
+struct Expansion {
+    scratch: Graph,
+    steps: Vec<InferenceRule>,
+}
+
+impl Expansion {
+    fn push(&mut self, step: InferenceRule) -> ProofResult<()> {
+        Action::apply_actions(step.gen_actions(&self.scratch)?, &mut self.scratch)?;
+        self.steps.push(step);
+
+        Ok(())
+    }
+}
+
+/// splits `[P, [Q]]` into the atoms and subgraphs of `P`, and the `[Q]` cut
+#[instrument]
+fn split_implication(
+    graph: &Graph,
+    implication: &GraphKey,
+) -> ProofResult<(Vec<Arc<Atom>>, Vec<GraphKey>, GraphKey)> {
+    let mut subgraphs = graph
+        .subgraphs_of(implication)?
+        .iter()
+        .cloned()
+        .collect_vec();
+
+    let consequent = subgraphs.pop().ok_or(ProofError::WrongShape {
+        target: *implication,
+        expected: "[P, [Q]]",
+    })?;
+
+    Ok((
+        graph.atoms_of(implication)?.iter().cloned().collect(),
+        subgraphs,
+        consequent,
+    ))
+}
+
+/// finds a subgraph of `area` that is a copy of the graph `copy_of` represents
+#[instrument]
+fn find_copy(graph: &Graph, area: &GraphKey, copy_of: &str) -> GraphResult<Option<GraphKey>> {
+    for k in graph.subgraphs_of(area)? {
+        if transform_graph_into_canonical_string(graph, k)? == copy_of {
+            return Ok(Some(*k));
+        }
+    }
+
+    Ok(None)
+}
+
+/// checks that `negation` sits in `area`, and finds its copy inside `target`
+#[instrument]
+fn find_negation_copy(
+    graph: &Graph,
+    area: &GraphKey,
+    negation: &GraphKey,
+    target: &GraphKey,
+) -> ProofResult<GraphKey> {
+    if graph.parent_of(negation)? != area {
+        Err(ProofError::ElementNotInArea {
+            area: *area,
+            element: Element::Subgraph(*negation),
+        })?
+    }
+
+    find_copy(
+        graph,
+        target,
+        &transform_graph_into_canonical_string(graph, negation)?,
+    )?
+    .ok_or(ProofError::ElementNotInArea {
+        area: *target,
+        element: Element::Subgraph(*negation),
+    })
+}
+
+/// the canonical string of an area that would hold exactly the given atoms and subgraphs
+#[instrument]
+pub(super) fn canonical_selection(
+    graph: &Graph,
+    atoms: &[Arc<Atom>],
+    subgraphs: &[GraphKey],
+) -> GraphResult<String> {
+    Ok(format!(
+        "[{}]",
+        Iterator::chain(
+            atoms.iter().map(|a| escape_atom(&a.to_string())).sorted(),
+            subgraphs
+                .iter()
+                .map(|k| transform_graph_into_canonical_string(graph, k))
+                .collect::<GraphResult<Vec<_>>>()?
+                .into_iter()
+                .sorted()
+        )
+        .join(",")
+    ))
+}
+
+#[instrument]
+fn split_element(element: Element) -> (Vec<Arc<Atom>>, Vec<GraphKey>) {
+    match element {
+        Element::Atom(a) => (vec![a], vec![]),
+        Element::Subgraph(k) => (vec![], vec![k]),
+    }
+}
+
+/// writes the rule as `name field=value ...`, like [`InferenceRule`] does
+impl Display for DerivedRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())?;
+
+        match self {
+            DerivedRule::ModusPonens {
+                premise: Element::Atom(a),
+                implication,
+            } => write!(
+                f,
+                " implication={} atom={}",
+                implication,
+                escape_field(&a.to_string(), "")
+            ),
+            DerivedRule::ModusPonens {
+                premise: Element::Subgraph(k),
+                implication,
+            } => write!(f, " implication={} subgraph={}", implication, k),
+            DerivedRule::ModusTollens {
+                negation,
+                implication,
+            } => write!(f, " implication={} negation={}", implication, negation),
+            DerivedRule::DisjunctiveSyllogism {
+                negation,
+                disjunction,
+            } => write!(f, " disjunction={} negation={}", disjunction, negation),
+            DerivedRule::Contraposition { implication } => {
+                write!(f, " implication={}", implication)
+            }
+            DerivedRule::HypotheticalSyllogism { first, second } => {
+                write!(f, " first={} second={}", first, second)
+            }
+        }
+    }
+}
+
+impl FromStr for DerivedRule {
+    type Err = ProofError;
+
+    #[instrument]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let name = words
+            .next()
+            .ok_or_else(|| ProofError::RuleParseError(s.to_string()))?;
+
+        let fields = words
+            .map(|w| {
+                w.split_once('=')
+                    .ok_or_else(|| ProofError::RuleParseError(format!("{} is not a field", w)))
+            })
+            .collect::<ProofResult<HashMap<_, _>>>()?;
+
+        let field = |k: &str| {
+            fields
+                .get(k)
+                .copied()
+                .ok_or_else(|| ProofError::RuleParseError(format!("{} is missing {}", name, k)))
+        };
+        let key = |k: &str| {
+            let v = field(k)?;
+            GraphKey::try_from_str(v)
+                .map_err(|_| ProofError::RuleParseError(format!("{} is not a valid id", v)))
+        };
+
+        match name {
+            "modus_ponens" => Ok(DerivedRule::ModusPonens {
+                premise: match fields.get("atom") {
+                    Some(a) => Element::Atom(Arc::new(Atom::from(unescape_field(a)?))),
+                    None => Element::Subgraph(key("subgraph")?),
+                },
+                implication: key("implication")?,
+            }),
+            "modus_tollens" => Ok(DerivedRule::ModusTollens {
+                negation: key("negation")?,
+                implication: key("implication")?,
+            }),
+            "disjunctive_syllogism" => Ok(DerivedRule::DisjunctiveSyllogism {
+                negation: key("negation")?,
+                disjunction: key("disjunction")?,
+            }),
+            "contraposition" => Ok(DerivedRule::Contraposition {
+                implication: key("implication")?,
+            }),
+            "hypothetical_syllogism" => Ok(DerivedRule::HypotheticalSyllogism {
+                first: key("first")?,
+                second: key("second")?,
+            }),
+            _ => Err(ProofError::UnknownRule(name.to_string())),
+        }
+    }
+}
@@ -1,15 +1,175 @@
+use crate::{
+    atom::Atom,
+    graph::{error::GraphError, GraphKey},
+    proof::action::error::ActionError,
+};
+use std::{fmt::Display, sync::Arc};
 use thiserror::Error;
 
-use crate::graph::error::GraphError;
-
 #[derive(Error, Debug)]
 #[non_exhaustive]
 pub enum ProofError {
-    #[error("Invalid application of rule: {0}")]
-    InvalidApplicationOfRule(String),
+    #[error("{target} is in an area on level {level}, but this rule needs an {expected} level")]
+    WrongParity {
+        target: GraphKey,
+        expected: Parity,
+        level: usize,
+    },
+
+    #[error("{target} is the sheet of assertion, which this rule cannot remove")]
+    SheetTargeted { target: GraphKey },
+
+    #[error("{element} is not directly contained in {area}")]
+    ElementNotInArea { area: GraphKey, element: Element },
+
+    #[error("{context} does not contain a copy of {element}")]
+    ElementNotInContext { context: GraphKey, element: Element },
+
+    #[error("{target} is not enclosed by {outer}")]
+    NotEnclosed { outer: GraphKey, target: GraphKey },
+
+    #[error("{target} is part of the selected subgraph {subgraph}")]
+    TargetInsideSelection {
+        target: GraphKey,
+        subgraph: GraphKey,
+    },
+
+    #[error("The outer cut {target} of a double cut should contain nothing except the inner cut")]
+    OuterCutNotEmpty {
+        target: GraphKey,
+        atoms: Vec<Arc<Atom>>,
+        subgraphs: Vec<GraphKey>,
+    },
+
+    #[error("{target} does not contain an inner cut")]
+    MissingInnerCut { target: GraphKey },
+
+    #[error("{target} does not have the form {expected}")]
+    WrongShape {
+        target: GraphKey,
+        expected: &'static str,
+    },
+
+    #[error("The selection in {area} is not an instance of the premise of lemma {lemma}")]
+    NotAnInstance { area: GraphKey, lemma: String },
+
+    #[error("No lemma named {0} is known")]
+    UnknownLemma(String),
+
+    #[error("Rule {0} is disabled in this rule set")]
+    RuleDisabled(String),
+
+    #[error("Not allowed at {target}: {restriction}")]
+    RestrictedByRuleSet {
+        target: GraphKey,
+        restriction: &'static str,
+    },
+
+    #[error("Rule {0} cannot be expanded into primitive steps")]
+    NotExpandable(String),
+
+    #[error("Could not parse rule: {0}")]
+    RuleParseError(String),
+
+    #[error("Could not parse problem: {0}")]
+    ProblemParseError(String),
+
+    #[error("Could not parse proof: {0}")]
+    ProofParseError(String),
+
+    #[error("No rule named {0} is registered")]
+    UnknownRule(String),
+
+    #[error("Expected a {0} rule")]
+    MismatchedRule(&'static str),
+
+    #[error("Error applying actions: {0}")]
+    ActionError(#[from] ActionError),
 
     #[error("Error operating on graph: {0}")]
     GraphError(#[from] GraphError),
 }
 
+impl ProofError {
+    /// the subgraphs involved in the error, for highlighting in frontends
+    pub fn offending_keys(&self) -> Vec<GraphKey> {
+        match self {
+            ProofError::WrongParity { target, .. } => vec![*target],
+            ProofError::ElementNotInArea { area, element }
+            | ProofError::ElementNotInContext {
+                context: area,
+                element,
+            } => match element {
+                Element::Atom(_) => vec![*area],
+                Element::Subgraph(k) => vec![*area, *k],
+            },
+            ProofError::NotEnclosed { outer, target } => vec![*outer, *target],
+            ProofError::TargetInsideSelection { target, subgraph } => vec![*target, *subgraph],
+            ProofError::OuterCutNotEmpty {
+                target, subgraphs, ..
+            } => [*target]
+                .into_iter()
+                .chain(subgraphs.iter().cloned())
+                .collect(),
+            ProofError::SheetTargeted { target }
+            | ProofError::MissingInnerCut { target }
+            | ProofError::WrongShape { target, .. } => {
+                vec![*target]
+            }
+            ProofError::NotAnInstance { area, .. } => vec![*area],
+            ProofError::RestrictedByRuleSet { target, .. } => vec![*target],
+            ProofError::RuleParseError(_)
+            | ProofError::ProblemParseError(_)
+            | ProofError::ProofParseError(_)
+            | ProofError::UnknownRule(_)
+            | ProofError::UnknownLemma(_)
+            | ProofError::RuleDisabled(_)
+            | ProofError::NotExpandable(_)
+            | ProofError::MismatchedRule(_)
+            | ProofError::ActionError(_)
+            | ProofError::GraphError(_) => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    Even,
+    Odd,
+}
+
+impl Parity {
+    pub fn of(level: usize) -> Self {
+        match level % 2 {
+            0 => Parity::Even,
+            _ => Parity::Odd,
+        }
+    }
+}
+
+impl Display for Parity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Parity::Even => write!(f, "even"),
+            Parity::Odd => write!(f, "odd"),
+        }
+    }
+}
+
+/// something selected by a rule: either an atom or a whole subgraph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Element {
+    Atom(Arc<Atom>),
+    Subgraph(GraphKey),
+}
+
+impl Display for Element {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Element::Atom(a) => write!(f, "atom {}", a),
+            Element::Subgraph(k) => write!(f, "subgraph {}", k),
+        }
+    }
+}
+
 pub type ProofResult<T> = Result<T, ProofError>;
@@ -0,0 +1,199 @@
+use super::{
+    error::ProofResult,
+    inference_rule::{DoubleCutDraw, DoubleCutErase, Erasure, InferenceRule, Insertion, Iteration},
+    rule_set::RuleSet,
+    search::{search_steps, SearchLimits},
+};
+use crate::{
+    atom::Atom,
+    graph::{
+        error::GraphResult, transform_graph_into_canonical_string, transform_graph_into_string,
+        Graph, GraphKey,
+    },
+};
+use itertools::Itertools;
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Display,
+    sync::Arc,
+};
+use tracing::instrument;
+
+/// a single step towards a goal, without giving away the rest of the proof
+#[derive(Debug, Clone)]
+pub struct Hint {
+    pub rule: InferenceRule,
+
+    /// what the step does, in terms of the graph and the goal
+    pub explanation: String,
+
+    /// how many steps are left once this one is taken
+    pub remaining: usize,
+}
+
+impl Display for Hint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.explanation)
+    }
+}
+
+/// recommends the first step of the shortest proof of `goal` from `graph` that `rules` allows
+///
+/// returns `None` if `graph` already is the goal, or no proof was found within the limits
+#[instrument(skip(graph, goal))]
+pub fn hint(
+    graph: &Graph,
+    goal: &Graph,
+    rules: &RuleSet,
+    limits: &SearchLimits,
+) -> ProofResult<Option<Hint>> {
+    let Some(steps) = search_steps(graph, goal, rules, limits)? else {
+        return Ok(None);
+    };
+
+    let Some(rule) = steps.first().cloned() else {
+        return Ok(None);
+    };
+
+    Ok(Some(Hint {
+        explanation: explain(&rule, graph, goal)?,
+        remaining: steps.len() - 1,
+        rule,
+    }))
+}
+
+/// describes what applying `rule` to `graph` does, mentioning the parts of the goal involved
+#[instrument(skip(graph, goal))]
+fn explain(rule: &InferenceRule, graph: &Graph, goal: &Graph) -> GraphResult<String> {
+    let pieces = Pieces::of(goal)?;
+
+    Ok(match rule {
+        InferenceRule::DoubleCutErase(DoubleCutErase { target }) => {
+            match graph.subgraphs_of(target)?.front() {
+                Some(inner) => {
+                    let atoms = graph.atoms_of(inner)?.iter().cloned().collect_vec();
+                    let subgraphs = graph.subgraphs_of(inner)?.iter().cloned().collect_vec();
+
+                    if atoms.is_empty() && subgraphs.is_empty() {
+                        "Removes an empty double cut".to_string()
+                    } else {
+                        format!(
+                            "Removes a double cut around {}",
+                            pieces.name(graph, &atoms, &subgraphs)?
+                        )
+                    }
+                }
+                None => "Removes a double cut".to_string(),
+            }
+        }
+
+        InferenceRule::DoubleCutDraw(DoubleCutDraw {
+            target_atoms,
+            target_subgraphs,
+            ..
+        }) => {
+            if target_atoms.is_empty() && target_subgraphs.is_empty() {
+                "Draws an empty double cut, making room for an insertion".to_string()
+            } else {
+                format!(
+                    "Draws a double cut around {}",
+                    pieces.name(graph, target_atoms, target_subgraphs)?
+                )
+            }
+        }
+
+        InferenceRule::Insertion(Insertion { new_content, .. }) => {
+            let content = new_content
+                .strip_prefix('[')
+                .and_then(|v| v.strip_suffix(']'))
+                .unwrap_or(new_content);
+
+            if pieces.contains(content) {
+                format!("Inserts the goal's {} into a negative area", content)
+            } else {
+                format!("Inserts {} into a negative area", content)
+            }
+        }
+
+        InferenceRule::Erasure(Erasure {
+            target_subgraphs,
+            target_atoms,
+        }) => {
+            let atoms = target_atoms.iter().map(|(_, a)| a.clone()).collect_vec();
+            let name = pieces.name(graph, &atoms, target_subgraphs)?;
+
+            if name.starts_with("the goal's") {
+                format!("Erases {} from a positive area", name)
+            } else {
+                format!("Erases {}, which the goal doesn't need", name)
+            }
+        }
+
+        InferenceRule::Iteration(Iteration {
+            backwards: false,
+            parent_atoms,
+            parent_subgraphs,
+            ..
+        }) => format!(
+            "Copies {} into a deeper area",
+            pieces.name(graph, parent_atoms, parent_subgraphs)?
+        ),
+
+        InferenceRule::Iteration(Iteration {
+            backwards: true,
+            parent_atoms,
+            parent_subgraphs,
+            ..
+        }) => format!(
+            "Removes {}, which is a copy of something further out",
+            pieces.name(graph, parent_atoms, parent_subgraphs)?
+        ),
+    })
+}
+
+/// the atoms and canonical subgraphs that appear anywhere in a graph
+struct Pieces(HashSet<String>);
+
+impl Pieces {
+    fn of(graph: &Graph) -> GraphResult<Self> {
+        let mut ans = HashSet::new();
+
+        let mut queue = VecDeque::from([*graph.root_id()]);
+        while let Some(id) = queue.pop_front() {
+            ans.extend(graph.atoms_of(&id)?.iter().map(|a| a.to_string()));
+
+            for k in graph.subgraphs_of(&id)? {
+                ans.insert(transform_graph_into_canonical_string(graph, k)?);
+                queue.push_back(*k);
+            }
+        }
+
+        Ok(Self(ans))
+    }
+
+    fn contains(&self, piece: &str) -> bool {
+        self.0.contains(piece)
+    }
+
+    /// lists the selection, calling it the goal's if all of it appears in the goal
+    fn name(
+        &self,
+        graph: &Graph,
+        atoms: &[Arc<Atom>],
+        subgraphs: &[GraphKey],
+    ) -> GraphResult<String> {
+        let mut in_goal = atoms.iter().all(|a| self.contains(&a.to_string()));
+        let mut names = atoms.iter().map(|a| a.to_string()).collect_vec();
+
+        for k in subgraphs {
+            in_goal &= self.contains(&transform_graph_into_canonical_string(graph, k)?);
+            names.push(transform_graph_into_string(graph, k)?);
+        }
+
+        Ok(format!(
+            "{}{}",
+            if in_goal { "the goal's " } else { "" },
+            names.join(", ")
+        ))
+    }
+}
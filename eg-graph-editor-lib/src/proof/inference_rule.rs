@@ -4,101 +4,553 @@ mod erasure;
 mod insertion;
 mod iteration;
 
-use self::{
-    double_cut_draw::gen_actions_from_double_cut_draw,
-    double_cut_erase::gen_actions_from_double_cut_erase, erasure::gen_actions_from_erasure,
-    insertion::gen_actions_from_insertion, iteration::gen_actions_from_iteration,
+pub use self::{
+    double_cut_draw::DoubleCutDraw, double_cut_erase::DoubleCutErase, erasure::Erasure,
+    insertion::Insertion, iteration::Iteration,
+};
+use super::{
+    action::{Action, GraphTarget},
+    error::{Element, Parity, ProofError, ProofResult},
+    rule::{unescape_field, Rule},
+    rule_set::{DoubleCuts, RuleSet},
 };
-use super::{action::Action, error::ProofResult};
 use crate::{
     atom::Atom,
     graph::{Graph, GraphKey},
 };
-use std::{fmt::Debug, sync::Arc};
+use itertools::Itertools;
+use std::{
+    collections::{HashMap, LinkedList, VecDeque},
+    fmt::{Debug, Display},
+    iter,
+    str::FromStr,
+    sync::Arc,
+};
 use tracing::instrument;
 
-#[derive(Debug)]
+/// one of the built in rules, each of which is also a [`Rule`] of its own
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum InferenceRule {
-    DoubleCutDraw {
-        /// the parent subgraph that contains the things we want to include in the double cut
-        target: GraphKey,
-
-        /// the atoms we want to include
-        target_atoms: Vec<Arc<Atom>>,
-
-        /// the subgraphs we want to include
-        target_subgraphs: Vec<GraphKey>,
-    },
-    DoubleCutErase {
-        /// the outer ring of the double cut
-        target: GraphKey,
-    },
-    Insertion {
-        /// target subgraph to insert in
-        target: GraphKey,
-
-        /// string representation of what to insert; needs to be valid graph syntax
-        new_content: String,
-    },
-    Erasure {
-        /// subgraphs to delete
-        target_subgraphs: Vec<GraphKey>,
-
-        // atoms to delete, in (subgraph_containing_atom, atom) format
-        target_atoms: Vec<(GraphKey, Arc<Atom>)>,
-    },
-    Iteration {
-        /// whether this is iteration (false) or deiteration(true)
-        backwards: bool,
-
-        /// the parent subgraph that contains the things we want to include in the iteration
-        parent: GraphKey,
-
-        /// the atoms we want to include
-        parent_atoms: Vec<Arc<Atom>>,
-
-        /// the subgraphs we want to include
-        parent_subgraphs: Vec<GraphKey>,
-
-        /// where the selected atoms/subgraphs should go
-        target: GraphKey,
-    },
+    DoubleCutDraw(DoubleCutDraw),
+    DoubleCutErase(DoubleCutErase),
+    Insertion(Insertion),
+    Erasure(Erasure),
+    Iteration(Iteration),
 }
 
 impl InferenceRule {
+    /// the rule this is, as a [`Rule`]
+    pub fn as_rule(&self) -> &dyn Rule {
+        match self {
+            InferenceRule::DoubleCutDraw(v) => v,
+            InferenceRule::DoubleCutErase(v) => v,
+            InferenceRule::Insertion(v) => v,
+            InferenceRule::Erasure(v) => v,
+            InferenceRule::Iteration(v) => v,
+        }
+    }
+
+    /// generates the actions that apply the rule, checked against the default [`RuleSet`]
     #[instrument]
-    pub fn gen_actions_from_rule(
+    pub fn gen_actions_from_rule(&self, graph: &Graph) -> ProofResult<LinkedList<Action>> {
+        self.as_rule()
+            .gen_actions_with_rules(graph, &RuleSet::default())
+    }
+
+    /// the same rule, but with every subgraph id passed through `f`
+    pub fn map_keys(&self, f: impl Fn(GraphKey) -> GraphKey) -> Self {
+        match self {
+            InferenceRule::DoubleCutDraw(v) => v.map_keys(&f).into(),
+            InferenceRule::DoubleCutErase(v) => v.map_keys(&f).into(),
+            InferenceRule::Insertion(v) => v.map_keys(&f).into(),
+            InferenceRule::Erasure(v) => v.map_keys(&f).into(),
+            InferenceRule::Iteration(v) => v.map_keys(&f).into(),
+        }
+    }
+
+    /// every rule application on `graph` that `rules` allows
+    ///
+    /// double cuts are drawn around every subset of every area, so the list grows quickly with
+    /// the size of areas; the applications are only worked out as they are taken, so take only
+    /// as many as needed. anything can be inserted into an odd area, so insertions are only
+    /// listed once per odd area, with `new_content` left empty for the caller to fill in
+    pub fn applicable<'a>(
+        graph: &'a Graph,
+        rules: &'a RuleSet,
+    ) -> impl Iterator<Item = InferenceRule> + 'a {
+        Self::applicable_with(graph, rules, Draws::EverySubset)
+    }
+
+    /// like [`InferenceRule::applicable`], but double cuts are only drawn around single elements
+    /// and around the whole area, so that the number of applications stays polynomial in the
+    /// size of the graph
+    pub fn applicable_bounded<'a>(
+        graph: &'a Graph,
+        rules: &'a RuleSet,
+    ) -> impl Iterator<Item = InferenceRule> + 'a {
+        Self::applicable_with(graph, rules, Draws::SinglesAndWhole)
+    }
+
+    fn applicable_with<'a>(
+        graph: &'a Graph,
+        rules: &'a RuleSet,
+        draws: Draws,
+    ) -> impl Iterator<Item = InferenceRule> + 'a {
+        let mut areas = Vec::new();
+
+        let mut queue = VecDeque::from([*graph.root_id()]);
+        while let Some(area) = queue.pop_front() {
+            queue.extend(graph.subgraphs_of(&area).into_iter().flatten().cloned());
+            areas.push(area);
+        }
+
+        areas
+            .into_iter()
+            .flat_map(move |area| Self::applicable_in(graph, rules, area, draws))
+    }
+
+    /// the legal rule applications that work on the contents of `area`
+    fn applicable_in<'a>(
+        graph: &'a Graph,
+        rules: &'a RuleSet,
+        area: GraphKey,
+        draws: Draws,
+    ) -> impl Iterator<Item = InferenceRule> + 'a {
+        let atoms = graph
+            .atoms_of(&area)
+            .into_iter()
+            .flatten()
+            .cloned()
+            .sorted_by_key(|a| a.to_string())
+            .collect_vec();
+        let subgraphs = graph
+            .subgraphs_of(&area)
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect_vec();
+
+        // every element on its own
+        let singles = Arc::new(
+            Iterator::chain(
+                atoms.iter().map(|a| (vec![a.clone()], vec![])),
+                subgraphs.iter().map(|k| (vec![], vec![*k])),
+            )
+            .collect::<Vec<(Vec<Arc<Atom>>, Vec<GraphKey>)>>(),
+        );
+
+        let even = graph
+            .level_of(&area)
+            .is_ok_and(|v| Parity::of(v) == Parity::Even);
+
+        let insertion = (!even && rules.check_enabled("insertion").is_ok()).then_some(
+            InferenceRule::Insertion(Insertion {
+                target: area,
+                new_content: String::new(),
+            }),
+        );
+
+        // double cuts are listed below, since they can go around more than one element
+        let on_singles = (0..singles.len()).flat_map({
+            let singles = singles.clone();
+            move |i| {
+                let (atoms, subgraphs) = singles[i].clone();
+                Self::applicable_to(graph, rules, area, atoms, subgraphs)
+                    .filter(|v| !matches!(v, InferenceRule::DoubleCutDraw(_)))
+            }
+        });
+
+        // a double cut can go around any subset, so those are only worked out when asked for;
+        // subsets the rule set doesn't allow aren't even tried, since there are too many of them
+        let n = singles.len();
+        let subsets: Box<dyn Iterator<Item = Vec<usize>>> = match draws {
+            _ if rules.check_enabled("double_cut_draw").is_err() => Box::new(iter::empty()),
+            _ if rules.double_cuts == DoubleCuts::AroundNothing => Box::new(iter::once(vec![])),
+            Draws::EverySubset => Box::new((0..n).powerset()),
+            Draws::SinglesAndWhole => Box::new(
+                [vec![]]
+                    .into_iter()
+                    .chain((0..n).map(|i| vec![i]))
+                    .chain([(0..n).collect()])
+                    .unique(),
+            ),
+        };
+        let draws = subsets.map(move |subset| {
+            let (target_atoms, target_subgraphs) = subset.into_iter().map(|i| &singles[i]).fold(
+                (vec![], vec![]),
+                |(mut a, mut s), (atoms, subgraphs)| {
+                    a.extend(atoms.iter().cloned());
+                    s.extend(subgraphs.iter().cloned());
+                    (a, s)
+                },
+            );
+
+            InferenceRule::DoubleCutDraw(DoubleCutDraw {
+                target: area,
+                target_atoms,
+                target_subgraphs,
+            })
+        });
+
+        on_singles.chain(insertion).chain(draws)
+    }
+
+    /// every rule application on exactly `atoms` and `subgraphs`, all of which are in `area`,
+    /// that `rules` allows; with nothing selected, the only thing left is drawing an empty double
+    /// cut into `area`
+    pub fn applicable_to<'a>(
+        graph: &'a Graph,
+        rules: &'a RuleSet,
+        area: GraphKey,
+        atoms: Vec<Arc<Atom>>,
+        subgraphs: Vec<GraphKey>,
+    ) -> impl Iterator<Item = InferenceRule> + 'a {
+        let mut ans = Vec::new();
+
+        if !atoms.is_empty() || !subgraphs.is_empty() {
+            if let ([], [k]) = (&atoms[..], &subgraphs[..]) {
+                ans.push(InferenceRule::DoubleCutErase(DoubleCutErase { target: *k }));
+            }
+
+            ans.push(InferenceRule::Erasure(Erasure {
+                target_subgraphs: subgraphs.clone(),
+                target_atoms: atoms.iter().map(|a| (area, a.clone())).collect(),
+            }));
+
+            // the area itself is left to the rule set to allow or not
+            let mut descendants = vec![area];
+            let mut queue = VecDeque::from_iter(graph.subgraphs_of(&area).into_iter().flatten());
+            while let Some(k) = queue.pop_front() {
+                queue.extend(graph.subgraphs_of(k).into_iter().flatten());
+                descendants.push(*k);
+            }
+
+            let mut ancestors = vec![area];
+            while let Some(parent) = ancestors.last().and_then(|v| graph.parent_of(v).ok()) {
+                ancestors.push(*parent);
+            }
+
+            for (backwards, targets) in [(false, descendants), (true, ancestors)] {
+                for target in targets {
+                    ans.push(InferenceRule::Iteration(Iteration {
+                        backwards,
+                        parent: area,
+                        parent_atoms: atoms.clone(),
+                        parent_subgraphs: subgraphs.clone(),
+                        target,
+                    }));
+                }
+            }
+        }
+
+        ans.push(InferenceRule::DoubleCutDraw(DoubleCutDraw {
+            target: area,
+            target_atoms: atoms,
+            target_subgraphs: subgraphs,
+        }));
+
+        ans.into_iter()
+            .filter(move |v| v.gen_actions_with_rules(graph, rules).is_ok())
+    }
+}
+
+/// which double cuts [`InferenceRule::applicable_in`] lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Draws {
+    EverySubset,
+    SinglesAndWhole,
+}
+
+impl From<DoubleCutDraw> for InferenceRule {
+    fn from(value: DoubleCutDraw) -> Self {
+        InferenceRule::DoubleCutDraw(value)
+    }
+}
+
+impl From<DoubleCutErase> for InferenceRule {
+    fn from(value: DoubleCutErase) -> Self {
+        InferenceRule::DoubleCutErase(value)
+    }
+}
+
+impl From<Insertion> for InferenceRule {
+    fn from(value: Insertion) -> Self {
+        InferenceRule::Insertion(value)
+    }
+}
+
+impl From<Erasure> for InferenceRule {
+    fn from(value: Erasure) -> Self {
+        InferenceRule::Erasure(value)
+    }
+}
+
+impl From<Iteration> for InferenceRule {
+    fn from(value: Iteration) -> Self {
+        InferenceRule::Iteration(value)
+    }
+}
+
+impl From<InferenceRule> for Arc<dyn Rule> {
+    fn from(value: InferenceRule) -> Self {
+        match value {
+            InferenceRule::DoubleCutDraw(v) => Arc::new(v),
+            InferenceRule::DoubleCutErase(v) => Arc::new(v),
+            InferenceRule::Insertion(v) => Arc::new(v),
+            InferenceRule::Erasure(v) => Arc::new(v),
+            InferenceRule::Iteration(v) => Arc::new(v),
+        }
+    }
+}
+
+impl Rule for InferenceRule {
+    fn name(&self) -> &str {
+        self.as_rule().name()
+    }
+
+    fn describe(&self) -> String {
+        self.as_rule().describe()
+    }
+
+    fn gen_actions(&self, graph: &Graph) -> ProofResult<LinkedList<Action>> {
+        self.as_rule().gen_actions(graph)
+    }
+
+    fn gen_actions_with_rules(
         &self,
         graph: &Graph,
-    ) -> ProofResult<impl IntoIterator<Item = Action> + Debug> {
+        rules: &RuleSet,
+    ) -> ProofResult<LinkedList<Action>> {
+        self.as_rule().gen_actions_with_rules(graph, rules)
+    }
+
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    fn expand(&self, _graph: &Graph) -> ProofResult<Vec<InferenceRule>> {
+        Ok(vec![self.clone()])
+    }
+
+    fn map_keys(&self, f: &dyn Fn(GraphKey) -> GraphKey) -> Option<Arc<dyn Rule>> {
+        self.as_rule().map_keys(f)
+    }
+}
+
+/// writes the rule as `name field=value ...`, with lists separated by commas
+impl Display for InferenceRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            InferenceRule::DoubleCutDraw {
-                target: _,
-                target_atoms: _,
-                target_subgraphs: _,
-            } => gen_actions_from_double_cut_draw(self, graph),
-
-            InferenceRule::DoubleCutErase { target: _ } => {
-                gen_actions_from_double_cut_erase(self, graph)
-            }
+            InferenceRule::DoubleCutDraw(v) => write!(f, "{}", v),
+            InferenceRule::DoubleCutErase(v) => write!(f, "{}", v),
+            InferenceRule::Insertion(v) => write!(f, "{}", v),
+            InferenceRule::Erasure(v) => write!(f, "{}", v),
+            InferenceRule::Iteration(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl FromStr for InferenceRule {
+    type Err = ProofError;
+
+    #[instrument]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let name = words
+            .next()
+            .ok_or_else(|| ProofError::RuleParseError(s.to_string()))?;
 
-            InferenceRule::Insertion {
-                target: _,
-                new_content: _,
-            } => gen_actions_from_insertion(self, graph),
-
-            InferenceRule::Erasure {
-                target_subgraphs: _,
-                target_atoms: _,
-            } => gen_actions_from_erasure(self, graph),
-
-            InferenceRule::Iteration {
-                backwards: _,
-                parent: _,
-                parent_atoms: _,
-                parent_subgraphs: _,
-                target: _,
-            } => gen_actions_from_iteration(self, graph),
+        let fields = words
+            .map(|w| {
+                w.split_once('=')
+                    .ok_or_else(|| ProofError::RuleParseError(format!("{} is not a field", w)))
+            })
+            .collect::<ProofResult<HashMap<_, _>>>()?;
+
+        let field = |k: &str| {
+            fields
+                .get(k)
+                .copied()
+                .ok_or_else(|| ProofError::RuleParseError(format!("{} is missing {}", name, k)))
+        };
+        let key = |v: &str| {
+            GraphKey::try_from_str(v)
+                .map_err(|_| ProofError::RuleParseError(format!("{} is not a valid id", v)))
+        };
+        let keys = |k: &str| {
+            field(k)?
+                .split(',')
+                .filter(|v| !v.is_empty())
+                .map(key)
+                .collect::<ProofResult<Vec<_>>>()
+        };
+        let atoms = |k: &str| {
+            field(k)?
+                .split(',')
+                .filter(|v| !v.is_empty())
+                .map(|v| Ok(Arc::new(Atom::from(unescape_field(v)?))))
+                .collect::<ProofResult<Vec<_>>>()
+        };
+
+        match name {
+            "double_cut_draw" => Ok(InferenceRule::DoubleCutDraw(DoubleCutDraw {
+                target: key(field("target")?)?,
+                target_atoms: atoms("atoms")?,
+                target_subgraphs: keys("subgraphs")?,
+            })),
+            "double_cut_erase" => Ok(InferenceRule::DoubleCutErase(DoubleCutErase {
+                target: key(field("target")?)?,
+            })),
+            "insertion" => Ok(InferenceRule::Insertion(Insertion {
+                target: key(field("target")?)?,
+                new_content: unescape_field(field("content")?)?,
+            })),
+            "erasure" => Ok(InferenceRule::Erasure(Erasure {
+                target_subgraphs: keys("subgraphs")?,
+                target_atoms: field("atoms")?
+                    .split(',')
+                    .filter(|v| !v.is_empty())
+                    .map(|v| {
+                        let (k, a) = v.split_once(':').ok_or_else(|| {
+                            ProofError::RuleParseError(format!("{} is not in id:atom form", v))
+                        })?;
+
+                        Ok((key(k)?, Arc::new(Atom::from(unescape_field(a)?))))
+                    })
+                    .collect::<ProofResult<Vec<_>>>()?,
+            })),
+            "iteration" | "deiteration" => Ok(InferenceRule::Iteration(Iteration {
+                backwards: name == "deiteration",
+                parent: key(field("parent")?)?,
+                parent_atoms: atoms("atoms")?,
+                parent_subgraphs: keys("subgraphs")?,
+                target: key(field("target")?)?,
+            })),
+            _ => Err(ProofError::UnknownRule(name.to_string())),
         }
     }
 }
+
+impl FromStr for DoubleCutDraw {
+    type Err = ProofError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match InferenceRule::from_str(s)? {
+            InferenceRule::DoubleCutDraw(v) => Ok(v),
+            _ => Err(ProofError::MismatchedRule("double_cut_draw")),
+        }
+    }
+}
+
+impl FromStr for DoubleCutErase {
+    type Err = ProofError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match InferenceRule::from_str(s)? {
+            InferenceRule::DoubleCutErase(v) => Ok(v),
+            _ => Err(ProofError::MismatchedRule("double_cut_erase")),
+        }
+    }
+}
+
+impl FromStr for Insertion {
+    type Err = ProofError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match InferenceRule::from_str(s)? {
+            InferenceRule::Insertion(v) => Ok(v),
+            _ => Err(ProofError::MismatchedRule("insertion")),
+        }
+    }
+}
+
+impl FromStr for Erasure {
+    type Err = ProofError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match InferenceRule::from_str(s)? {
+            InferenceRule::Erasure(v) => Ok(v),
+            _ => Err(ProofError::MismatchedRule("erasure")),
+        }
+    }
+}
+
+impl FromStr for Iteration {
+    type Err = ProofError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match InferenceRule::from_str(s)? {
+            InferenceRule::Iteration(v) => Ok(v),
+            _ => Err(ProofError::MismatchedRule("iteration")),
+        }
+    }
+}
+
+#[instrument]
+pub(super) fn list_selection(atoms: &[Arc<Atom>], subgraphs: &[GraphKey]) -> String {
+    Iterator::chain(
+        atoms.iter().map(|a| a.to_string()),
+        subgraphs.iter().map(|k| k.to_string()),
+    )
+    .join(", ")
+}
+
+/// checks that every selected atom and subgraph sits directly in `area`
+#[instrument]
+pub(super) fn check_selection(
+    graph: &Graph,
+    area: &GraphKey,
+    atoms: &[Arc<Atom>],
+    subgraphs: &[GraphKey],
+) -> ProofResult<()> {
+    if let Some(a) = atoms
+        .iter()
+        .find(|a| !graph.atoms_of(area).is_ok_and(|v| v.contains(*a)))
+    {
+        Err(ProofError::ElementNotInArea {
+            area: *area,
+            element: Element::Atom(a.clone()),
+        })?
+    }
+
+    if let Some(v) = subgraphs
+        .iter()
+        .find(|v| !graph.subgraphs_of(area).is_ok_and(|s| s.contains(*v)))
+    {
+        Err(ProofError::ElementNotInArea {
+            area: *area,
+            element: Element::Subgraph(*v),
+        })?
+    }
+
+    Ok(())
+}
+
+/// generates the actions that delete the given subgraphs along with everything inside them
+#[instrument]
+pub(super) fn gen_actions_deleting_subgraphs(
+    graph: &Graph,
+    subgraphs: &[GraphKey],
+) -> ProofResult<LinkedList<Action>> {
+    let mut ans = LinkedList::new();
+
+    let mut queue = VecDeque::from_iter(subgraphs.iter().cloned());
+    while let Some(id) = queue.pop_front() {
+        for p in graph.subgraphs_of(&id)? {
+            queue.push_back(*p)
+        }
+
+        ans.push_front(Action::DeleteSubgraph {
+            target: GraphTarget::Exists(id),
+        });
+
+        for a in graph.atoms_of(&id)? {
+            ans.push_front(Action::DeleteAtom {
+                target: GraphTarget::Exists(id),
+                atom: (**a).clone(),
+            })
+        }
+    }
+
+    Ok(ans)
+}
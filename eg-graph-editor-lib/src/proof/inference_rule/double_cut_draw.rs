@@ -1,42 +1,83 @@
-use super::InferenceRule;
+use super::{check_selection, list_selection, InferenceRule};
 use crate::{
-    graph::Graph,
+    atom::Atom,
+    graph::{Graph, GraphKey},
     proof::{
         action::{Action, GraphTarget},
         error::{ProofError, ProofResult},
+        rule::{escape_field, Rule},
+        rule_set::{DoubleCuts, RuleSet},
     },
 };
-use fallible_iterator::{FallibleIterator, IteratorExt};
-use std::collections::LinkedList;
+use itertools::Itertools;
+use std::{collections::LinkedList, fmt::Display, sync::Arc};
 use tracing::instrument;
 
-#[instrument]
-pub fn gen_actions_from_double_cut_draw(
-    rule: &InferenceRule,
-    graph: &Graph,
-) -> ProofResult<LinkedList<Action>> {
-    if let InferenceRule::DoubleCutDraw {
-        target,
-        target_atoms,
-        target_subgraphs,
-    } = rule
-    {
+/// draws two cuts, one inside the other, around some of the contents of an area
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoubleCutDraw {
+    /// the parent subgraph that contains the things we want to include in the double cut
+    pub target: GraphKey,
+
+    /// the atoms we want to include
+    pub target_atoms: Vec<Arc<Atom>>,
+
+    /// the subgraphs we want to include
+    pub target_subgraphs: Vec<GraphKey>,
+}
+
+impl DoubleCutDraw {
+    /// the same rule, but with every subgraph id passed through `f`
+    pub fn map_keys(&self, f: &dyn Fn(GraphKey) -> GraphKey) -> Self {
+        Self {
+            target: f(self.target),
+            target_atoms: self.target_atoms.clone(),
+            target_subgraphs: self.target_subgraphs.iter().map(|k| f(*k)).collect(),
+        }
+    }
+}
+
+impl Rule for DoubleCutDraw {
+    fn name(&self) -> &str {
+        "double_cut_draw"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "Draw a double cut in {} around [{}]",
+            self.target,
+            list_selection(&self.target_atoms, &self.target_subgraphs)
+        )
+    }
+
+    fn gen_actions(&self, graph: &Graph) -> ProofResult<LinkedList<Action>> {
+        self.gen_actions_with_rules(graph, &RuleSet::default())
+    }
+
+    #[instrument]
+    fn gen_actions_with_rules(
+        &self,
+        graph: &Graph,
+        rules: &RuleSet,
+    ) -> ProofResult<LinkedList<Action>> {
+        let DoubleCutDraw {
+            target,
+            target_atoms,
+            target_subgraphs,
+        } = self;
+
         // check validity
 
-        if target_atoms
-            .iter()
-            .map(Ok::<_, ProofError>)
-            .transpose_into_fallible()
-            .any(|a| Ok(!graph.atoms_of(target)?.contains(a)))?
-            || target_subgraphs
-                .iter()
-                .map(Ok::<_, ProofError>)
-                .transpose_into_fallible()
-                .any(|v| Ok(!graph.subgraphs_of(target)?.contains(v)))?
+        rules.check_enabled(self.name())?;
+        check_selection(graph, target, target_atoms, target_subgraphs)?;
+
+        if rules.double_cuts == DoubleCuts::AroundNothing
+            && !(target_atoms.is_empty() && target_subgraphs.is_empty())
         {
-            Err(ProofError::InvalidApplicationOfRule(
-                "Target subgraph does not contain all selected elements".to_string(),
-            ))?
+            Err(ProofError::RestrictedByRuleSet {
+                target: *target,
+                restriction: "double cuts can only be drawn around nothing",
+            })?
         }
 
         // calculate the actions
@@ -72,7 +113,33 @@ pub fn gen_actions_from_double_cut_draw(
         }
 
         Ok(ans)
-    } else {
-        panic!("This method should only be used for drawing double cuts")
+    }
+
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    fn expand(&self, _graph: &Graph) -> ProofResult<Vec<InferenceRule>> {
+        Ok(vec![self.clone().into()])
+    }
+
+    fn map_keys(&self, f: &dyn Fn(GraphKey) -> GraphKey) -> Option<Arc<dyn Rule>> {
+        Some(Arc::new(DoubleCutDraw::map_keys(self, f)))
+    }
+}
+
+impl Display for DoubleCutDraw {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} target={} atoms={} subgraphs={}",
+            self.name(),
+            self.target,
+            self.target_atoms
+                .iter()
+                .map(|a| escape_field(&a.to_string(), ",:"))
+                .join(","),
+            self.target_subgraphs.iter().join(",")
+        )
     }
 }
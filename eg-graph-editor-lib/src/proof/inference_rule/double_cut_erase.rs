@@ -1,39 +1,96 @@
 use super::InferenceRule;
 use crate::{
-    graph::Graph,
+    graph::{error::GraphError, Graph, GraphKey},
     proof::{
         action::{Action, GraphTarget},
         error::{ProofError, ProofResult},
+        rule::Rule,
+        rule_set::{DoubleCuts, RuleSet},
     },
 };
-use std::collections::LinkedList;
+use std::{collections::LinkedList, fmt::Display, sync::Arc};
 use tracing::instrument;
 
-#[instrument]
-pub fn gen_actions_from_double_cut_erase(
-    rule: &InferenceRule,
-    graph: &Graph,
-) -> ProofResult<LinkedList<Action>> {
-    if let InferenceRule::DoubleCutErase { target } = rule {
-        if !graph.atoms_of(target)?.is_empty() || graph.subgraphs_of(target)?.len() != 1 {
-            Err(ProofError::InvalidApplicationOfRule(
-                "The outer cut of a double cut should contain nothing except the inner cut"
-                    .to_string(),
-            ))?
+/// erases two cuts, one directly inside the other, leaving their contents in place
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoubleCutErase {
+    /// the outer ring of the double cut
+    pub target: GraphKey,
+}
+
+impl DoubleCutErase {
+    /// the same rule, but with every subgraph id passed through `f`
+    pub fn map_keys(&self, f: &dyn Fn(GraphKey) -> GraphKey) -> Self {
+        Self {
+            target: f(self.target),
+        }
+    }
+}
+
+impl Rule for DoubleCutErase {
+    fn name(&self) -> &str {
+        "double_cut_erase"
+    }
+
+    fn describe(&self) -> String {
+        format!("Erase the double cut starting at {}", self.target)
+    }
+
+    fn gen_actions(&self, graph: &Graph) -> ProofResult<LinkedList<Action>> {
+        self.gen_actions_with_rules(graph, &RuleSet::default())
+    }
+
+    #[instrument]
+    fn gen_actions_with_rules(
+        &self,
+        graph: &Graph,
+        rules: &RuleSet,
+    ) -> ProofResult<LinkedList<Action>> {
+        let target = &self.target;
+
+        rules.check_enabled(self.name())?;
+
+        let atoms = graph.atoms_of(target)?;
+        let subgraphs = graph.subgraphs_of(target)?;
+
+        if target == graph.root_id() {
+            Err(GraphError::InvalidRootGraphTargetError)?
+        } else if subgraphs.is_empty() {
+            Err(ProofError::MissingInnerCut { target: *target })?
+        } else if !atoms.is_empty() || subgraphs.len() != 1 {
+            Err(ProofError::OuterCutNotEmpty {
+                target: *target,
+                atoms: atoms.iter().cloned().collect(),
+                subgraphs: subgraphs.iter().skip(1).cloned().collect(),
+            })?
         } else {
             let mut ans = LinkedList::new();
             let inner_ring = *graph.subgraphs_of(target)?.iter().next().unwrap();
             let parent = *graph.parent_of(target)?;
 
+            if rules.double_cuts == DoubleCuts::AroundNothing
+                && !(graph.atoms_of(&inner_ring)?.is_empty()
+                    && graph.subgraphs_of(&inner_ring)?.is_empty())
+            {
+                Err(ProofError::RestrictedByRuleSet {
+                    target: *target,
+                    restriction: "double cuts can only be erased when empty",
+                })?
+            }
+
             for atom in graph.atoms_of(&inner_ring)? {
                 ans.push_back(Action::DeleteAtom {
                     target: GraphTarget::Exists(inner_ring),
                     atom: (**atom).clone(),
                 });
-                ans.push_back(Action::AddAtom {
-                    target: GraphTarget::Exists(parent),
-                    atom: (**atom).clone(),
-                });
+
+                // the parent may already have this atom, and areas can't hold duplicates
+                if !graph.atoms_of(&parent)?.contains(atom) {
+                    ans.push_back(Action::AddAtom {
+                        target: GraphTarget::Exists(parent),
+                        atom: (**atom).clone(),
+                    });
+                }
             }
 
             for subgraph in graph.subgraphs_of(&inner_ring)? {
@@ -53,7 +110,23 @@ pub fn gen_actions_from_double_cut_erase(
 
             Ok(ans)
         }
-    } else {
-        panic!("This method should only be used for erasing double cuts")
+    }
+
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    fn expand(&self, _graph: &Graph) -> ProofResult<Vec<InferenceRule>> {
+        Ok(vec![self.clone().into()])
+    }
+
+    fn map_keys(&self, f: &dyn Fn(GraphKey) -> GraphKey) -> Option<Arc<dyn Rule>> {
+        Some(Arc::new(DoubleCutErase::map_keys(self, f)))
+    }
+}
+
+impl Display for DoubleCutErase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} target={}", self.name(), self.target)
     }
 }
@@ -1,70 +1,141 @@
-use super::InferenceRule;
+use super::{gen_actions_deleting_subgraphs, InferenceRule};
 use crate::{
-    graph::Graph,
+    atom::Atom,
+    graph::{Graph, GraphKey},
     proof::{
         action::{Action, GraphTarget},
-        error::{ProofError, ProofResult},
+        error::{Parity, ProofError, ProofResult},
+        rule::{escape_field, Rule},
+        rule_set::RuleSet,
     },
 };
-use fallible_iterator::{FallibleIterator, IteratorExt};
-use std::collections::{LinkedList, VecDeque};
+use itertools::Itertools;
+use std::{collections::LinkedList, fmt::Display, sync::Arc};
 use tracing::instrument;
 
-#[instrument]
-pub fn gen_actions_from_erasure(
-    rule: &InferenceRule,
-    graph: &Graph,
-) -> ProofResult<LinkedList<Action>> {
-    if let InferenceRule::Erasure {
-        target_subgraphs,
-        target_atoms,
-    } = rule
-    {
-        if target_subgraphs
-            .iter()
-            .map(Ok::<_, ProofError>)
-            .transpose_into_fallible()
-            .any(|v| Ok(graph.level_of(v)? % 2 == 0))?
-            || target_atoms
+/// erases atoms and subgraphs from areas inside an even number of cuts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Erasure {
+    /// subgraphs to delete
+    pub target_subgraphs: Vec<GraphKey>,
+
+    /// atoms to delete, in (subgraph_containing_atom, atom) format
+    pub target_atoms: Vec<(GraphKey, Arc<Atom>)>,
+}
+
+impl Erasure {
+    /// the same rule, but with every subgraph id passed through `f`
+    pub fn map_keys(&self, f: &dyn Fn(GraphKey) -> GraphKey) -> Self {
+        Self {
+            target_subgraphs: self.target_subgraphs.iter().map(|k| f(*k)).collect(),
+            target_atoms: self
+                .target_atoms
                 .iter()
-                .map(Ok::<_, ProofError>)
-                .transpose_into_fallible()
-                .any(|(p, _)| Ok(graph.level_of(p)? % 2 == 1))?
-        {
-            Err(ProofError::InvalidApplicationOfRule(
-                "Erasure can only delete things from even levels".to_string(),
-            ))?
-        } else {
-            let mut ans = LinkedList::new();
-
-            let mut queue = VecDeque::from_iter(target_subgraphs.iter().cloned());
-            while let Some(id) = queue.pop_front() {
-                for p in graph.subgraphs_of(&id)? {
-                    queue.push_back(*p)
-                }
-
-                ans.push_front(Action::DeleteSubgraph {
-                    target: GraphTarget::Exists(id),
-                });
-
-                for a in graph.atoms_of(&id)? {
-                    ans.push_front(Action::DeleteAtom {
-                        target: GraphTarget::Exists(id),
-                        atom: (**a).clone(),
-                    })
-                }
+                .map(|(k, a)| (f(*k), a.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl Rule for Erasure {
+    fn name(&self) -> &str {
+        "erasure"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "Erase [{}]",
+            Iterator::chain(
+                self.target_atoms
+                    .iter()
+                    .map(|(k, a)| format!("{} in {}", a, k)),
+                self.target_subgraphs.iter().map(|k| k.to_string())
+            )
+            .join(", ")
+        )
+    }
+
+    fn gen_actions(&self, graph: &Graph) -> ProofResult<LinkedList<Action>> {
+        self.gen_actions_with_rules(graph, &RuleSet::default())
+    }
+
+    #[instrument]
+    fn gen_actions_with_rules(
+        &self,
+        graph: &Graph,
+        rules: &RuleSet,
+    ) -> ProofResult<LinkedList<Action>> {
+        let Erasure {
+            target_subgraphs,
+            target_atoms,
+        } = self;
+
+        rules.check_enabled(self.name())?;
+
+        for v in target_subgraphs {
+            if v == graph.root_id() {
+                Err(ProofError::SheetTargeted { target: *v })?
             }
 
-            for (p, a) in target_atoms {
-                ans.push_front(Action::DeleteAtom {
-                    target: GraphTarget::Exists(*p),
-                    atom: (**a).clone(),
-                })
+            let level = graph.level_of(v)?;
+
+            if level % 2 == 0 {
+                Err(ProofError::WrongParity {
+                    target: *v,
+                    expected: Parity::Even,
+                    level: level - 1,
+                })?
             }
+        }
 
-            Ok(ans)
+        for (p, _) in target_atoms {
+            let level = graph.level_of(p)?;
+
+            if level % 2 == 1 {
+                Err(ProofError::WrongParity {
+                    target: *p,
+                    expected: Parity::Even,
+                    level,
+                })?
+            }
+        }
+
+        let mut ans = gen_actions_deleting_subgraphs(graph, target_subgraphs)?;
+
+        for (p, a) in target_atoms {
+            ans.push_front(Action::DeleteAtom {
+                target: GraphTarget::Exists(*p),
+                atom: (**a).clone(),
+            })
         }
-    } else {
-        panic!("This method should only be used for erasure")
+
+        Ok(ans)
+    }
+
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    fn expand(&self, _graph: &Graph) -> ProofResult<Vec<InferenceRule>> {
+        Ok(vec![self.clone().into()])
+    }
+
+    fn map_keys(&self, f: &dyn Fn(GraphKey) -> GraphKey) -> Option<Arc<dyn Rule>> {
+        Some(Arc::new(Erasure::map_keys(self, f)))
+    }
+}
+
+impl Display for Erasure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} subgraphs={} atoms={}",
+            self.name(),
+            self.target_subgraphs.iter().join(","),
+            self.target_atoms
+                .iter()
+                .map(|(k, a)| format!("{}:{}", k, escape_field(&a.to_string(), ",:")))
+                .join(",")
+        )
     }
 }
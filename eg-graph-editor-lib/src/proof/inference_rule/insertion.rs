@@ -1,30 +1,73 @@
 use super::InferenceRule;
 use crate::{
-    graph::{parse_graph_string_into_actions, Graph},
+    graph::{parse_graph_string_into_actions, Graph, GraphKey},
     proof::{
         action::Action,
-        error::{ProofError, ProofResult},
+        error::{Parity, ProofError, ProofResult},
+        rule::{escape_field, Rule},
+        rule_set::RuleSet,
     },
 };
-use std::collections::LinkedList;
+use std::{collections::LinkedList, fmt::Display, sync::Arc};
 use tracing::instrument;
 
-#[instrument]
-pub fn gen_actions_from_insertion(
-    rule: &InferenceRule,
-    graph: &Graph,
-) -> ProofResult<LinkedList<Action>> {
-    if let InferenceRule::Insertion {
-        target,
-        new_content,
-    } = rule
-    {
+/// inserts any graph into an area inside an odd number of cuts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Insertion {
+    /// target subgraph to insert in
+    pub target: GraphKey,
+
+    /// string representation of what to insert; needs to be valid graph syntax
+    pub new_content: String,
+}
+
+impl Insertion {
+    /// the same rule, but with every subgraph id passed through `f`
+    pub fn map_keys(&self, f: &dyn Fn(GraphKey) -> GraphKey) -> Self {
+        Self {
+            target: f(self.target),
+            new_content: self.new_content.clone(),
+        }
+    }
+}
+
+impl Rule for Insertion {
+    fn name(&self) -> &str {
+        "insertion"
+    }
+
+    fn describe(&self) -> String {
+        if self.new_content.is_empty() {
+            format!("Insert anything into {}", self.target)
+        } else {
+            format!("Insert {} into {}", self.new_content, self.target)
+        }
+    }
+
+    fn gen_actions(&self, graph: &Graph) -> ProofResult<LinkedList<Action>> {
+        self.gen_actions_with_rules(graph, &RuleSet::default())
+    }
+
+    #[instrument]
+    fn gen_actions_with_rules(
+        &self,
+        graph: &Graph,
+        rules: &RuleSet,
+    ) -> ProofResult<LinkedList<Action>> {
+        let Insertion {
+            target,
+            new_content,
+        } = self;
+
+        rules.check_enabled(self.name())?;
+
         let target_level = graph.level_of(target)?;
         if target_level % 2 == 0 {
-            Err(ProofError::InvalidApplicationOfRule(format!(
-                "Selected subgraph must be on a odd level, but is on level {}",
-                target_level
-            )))?
+            Err(ProofError::WrongParity {
+                target: *target,
+                expected: Parity::Odd,
+                level: target_level,
+            })?
         } else {
             Ok(
                 parse_graph_string_into_actions(graph, new_content, *target)?
@@ -32,7 +75,29 @@ pub fn gen_actions_from_insertion(
                     .collect::<LinkedList<_>>(),
             )
         }
-    } else {
-        panic!("This method should only be used for insertion")
+    }
+
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    fn expand(&self, _graph: &Graph) -> ProofResult<Vec<InferenceRule>> {
+        Ok(vec![self.clone().into()])
+    }
+
+    fn map_keys(&self, f: &dyn Fn(GraphKey) -> GraphKey) -> Option<Arc<dyn Rule>> {
+        Some(Arc::new(Insertion::map_keys(self, f)))
+    }
+}
+
+impl Display for Insertion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} target={} content={}",
+            self.name(),
+            self.target,
+            escape_field(&self.new_content, "")
+        )
     }
 }
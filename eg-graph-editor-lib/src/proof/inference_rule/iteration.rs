@@ -1,70 +1,213 @@
-use super::InferenceRule;
+use super::{check_selection, gen_actions_deleting_subgraphs, list_selection, InferenceRule};
 use crate::{
-    graph::{parse_graph_string_into_actions, transform_graph_into_string, Graph},
+    atom::Atom,
+    graph::{
+        error::GraphResult, escape_atom, parse_graph_string_into_actions,
+        transform_graph_into_canonical_string, transform_graph_into_string, Graph, GraphKey,
+    },
     proof::{
-        action::Action,
-        error::{ProofError, ProofResult},
+        action::{Action, GraphTarget},
+        error::{Element, ProofError, ProofResult},
+        rule::{escape_field, Rule},
+        rule_set::RuleSet,
     },
 };
-use fallible_iterator::{FallibleIterator, IteratorExt};
 use itertools::Itertools;
-use std::collections::LinkedList;
+use std::{collections::LinkedList, fmt::Display, sync::Arc};
 use tracing::instrument;
 
-#[instrument]
-pub fn gen_actions_from_iteration(
-    rule: &InferenceRule,
-    graph: &Graph,
-) -> ProofResult<LinkedList<Action>> {
-    if let InferenceRule::Iteration {
-        backwards,
-        parent,
-        parent_atoms,
-        parent_subgraphs,
-        target,
-    } = rule
-    {
+/// copies atoms and subgraphs of an area into an area it encloses, or removes such copies again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Iteration {
+    /// whether this is iteration (false) or deiteration(true)
+    pub backwards: bool,
+
+    /// the parent subgraph that contains the things we want to include in the iteration
+    pub parent: GraphKey,
+
+    /// the atoms we want to include
+    pub parent_atoms: Vec<Arc<Atom>>,
+
+    /// the subgraphs we want to include
+    pub parent_subgraphs: Vec<GraphKey>,
+
+    /// where the selected atoms/subgraphs should go
+    pub target: GraphKey,
+}
+
+impl Iteration {
+    /// the same rule, but with every subgraph id passed through `f`
+    pub fn map_keys(&self, f: &dyn Fn(GraphKey) -> GraphKey) -> Self {
+        Self {
+            backwards: self.backwards,
+            parent: f(self.parent),
+            parent_atoms: self.parent_atoms.clone(),
+            parent_subgraphs: self.parent_subgraphs.iter().map(|k| f(*k)).collect(),
+            target: f(self.target),
+        }
+    }
+}
+
+impl Rule for Iteration {
+    fn name(&self) -> &str {
+        match self.backwards {
+            false => "iteration",
+            true => "deiteration",
+        }
+    }
+
+    fn describe(&self) -> String {
+        let selection = list_selection(&self.parent_atoms, &self.parent_subgraphs);
+
+        match self.backwards {
+            false => format!(
+                "Iterate [{}] from {} into {}",
+                selection, self.parent, self.target
+            ),
+            true => format!(
+                "Deiterate [{}] from {}, which are copies of things in {}",
+                selection, self.parent, self.target
+            ),
+        }
+    }
+
+    fn gen_actions(&self, graph: &Graph) -> ProofResult<LinkedList<Action>> {
+        self.gen_actions_with_rules(graph, &RuleSet::default())
+    }
+
+    #[instrument]
+    fn gen_actions_with_rules(
+        &self,
+        graph: &Graph,
+        rules: &RuleSet,
+    ) -> ProofResult<LinkedList<Action>> {
+        let Iteration {
+            backwards,
+            parent,
+            parent_atoms,
+            parent_subgraphs,
+            target,
+        } = self;
+
         // check validity
-        let parent_doesnt_contains_all_atoms = parent_atoms
-            .iter()
-            .map(Ok::<_, ProofError>)
-            .transpose_into_fallible()
-            .any(|a| Ok(!graph.atoms_of(parent)?.contains(a)))?;
-
-        let parent_doesnt_contains_all_subgraphs = parent_subgraphs
-            .iter()
-            .map(Ok::<_, ProofError>)
-            .transpose_into_fallible()
-            .any(|v| Ok(!graph.subgraphs_of(parent)?.contains(v)))?;
-
-        let correct_ancestry = (!backwards && graph.is_related_to(parent, target)?)
-            || (*backwards && graph.is_related_to(target, parent)?);
-
-        if parent_doesnt_contains_all_atoms
-            || parent_doesnt_contains_all_subgraphs
-            || !correct_ancestry
-        {
-            Err(ProofError::InvalidApplicationOfRule(
-                "Invalid Selection".to_string(),
-            ))?
+        rules.check_enabled(self.name())?;
+        check_selection(graph, parent, parent_atoms, parent_subgraphs)?;
+
+        if !rules.iteration_within_area && target == parent {
+            Err(ProofError::RestrictedByRuleSet {
+                target: *target,
+                restriction: "iteration has to go into an enclosed area",
+            })?
+        }
+
+        if *backwards {
+            // the selection is the copy, and target is where the original lives
+            if !graph.is_related_to(target, parent)? {
+                Err(ProofError::NotEnclosed {
+                    outer: *target,
+                    target: *parent,
+                })?
+            }
+
+            for a in parent_atoms {
+                if target == parent || !graph.atoms_of(target)?.contains(a) {
+                    Err(ProofError::ElementNotInContext {
+                        context: *target,
+                        element: Element::Atom(a.clone()),
+                    })?
+                }
+            }
+
+            for v in parent_subgraphs {
+                let copy = transform_graph_into_canonical_string(graph, v)?;
+
+                let has_original = graph
+                    .subgraphs_of(target)?
+                    .iter()
+                    .filter(|k| *k != v)
+                    .map(|k| transform_graph_into_canonical_string(graph, k))
+                    .collect::<GraphResult<Vec<_>>>()?
+                    .contains(&copy);
+
+                if !has_original {
+                    Err(ProofError::ElementNotInContext {
+                        context: *target,
+                        element: Element::Subgraph(*v),
+                    })?
+                }
+            }
+
+            let mut ans = gen_actions_deleting_subgraphs(graph, parent_subgraphs)?;
+
+            for a in parent_atoms {
+                ans.push_front(Action::DeleteAtom {
+                    target: GraphTarget::Exists(*parent),
+                    atom: (**a).clone(),
+                })
+            }
+
+            Ok(ans)
         } else {
+            if !graph.is_related_to(parent, target)? {
+                Err(ProofError::NotEnclosed {
+                    outer: *parent,
+                    target: *target,
+                })?
+            }
+
+            for v in parent_subgraphs {
+                if graph.is_related_to(v, target)? {
+                    Err(ProofError::TargetInsideSelection {
+                        target: *target,
+                        subgraph: *v,
+                    })?
+                }
+            }
+
             // should probably optimize this at some point, but it works for now
 
             let str_graph = format!(
                 "[{}]",
                 Iterator::chain(
-                    parent_atoms.iter().map(|e| e.to_string()),
+                    parent_atoms.iter().map(|e| Ok(escape_atom(&e.to_string()))),
                     parent_subgraphs
                         .iter()
                         .map(|e| transform_graph_into_string(graph, e))
                 )
-                .intersperse(",".to_string())
-                .collect::<String>()
+                .collect::<GraphResult<Vec<_>>>()?
+                .join(",")
             );
 
             Ok(parse_graph_string_into_actions(graph, &str_graph, *target)?)
         }
-    } else {
-        panic!("This method should only be used for erasure")
+    }
+
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    fn expand(&self, _graph: &Graph) -> ProofResult<Vec<InferenceRule>> {
+        Ok(vec![self.clone().into()])
+    }
+
+    fn map_keys(&self, f: &dyn Fn(GraphKey) -> GraphKey) -> Option<Arc<dyn Rule>> {
+        Some(Arc::new(Iteration::map_keys(self, f)))
+    }
+}
+
+impl Display for Iteration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} parent={} atoms={} subgraphs={} target={}",
+            self.name(),
+            self.parent,
+            self.parent_atoms
+                .iter()
+                .map(|a| escape_field(&a.to_string(), ",:"))
+                .join(","),
+            self.parent_subgraphs.iter().join(","),
+            self.target
+        )
     }
 }
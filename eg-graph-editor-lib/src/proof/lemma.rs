@@ -0,0 +1,600 @@
+use super::{
+    action::{Action, GraphTarget},
+    derived_rule::canonical_selection,
+    error::{Parity, ProofError, ProofResult},
+    inference_rule::{
+        check_selection, gen_actions_deleting_subgraphs, list_selection, DoubleCutDraw,
+        DoubleCutErase, Erasure, InferenceRule, Insertion, Iteration,
+    },
+    rule::{escape_field, unescape_field, Rule, RuleRegistry},
+    rule_set::RuleSet,
+};
+use crate::{
+    atom::Atom,
+    graph::{
+        error::GraphResult, parse_graph_string_into_actions, transform_graph_into_canonical_string,
+        transform_graph_into_keyed_string, transform_graph_into_string,
+        transform_substituted_graph_into_canonical_string, transform_substituted_graph_into_string,
+        Graph, GraphKey, Substitution,
+    },
+};
+use hashlink::LinkedHashMap;
+use itertools::Itertools;
+use std::{
+    collections::{HashMap, HashSet, LinkedList, VecDeque},
+    fmt::{Debug, Display},
+    str::FromStr,
+    sync::Arc,
+};
+use tracing::instrument;
+
+/// a proof of `conclusion` from `premise`, which can be reused wherever an instance of the
+/// premise appears in a positive area
+#[derive(Debug, Clone)]
+pub struct Lemma {
+    name: String,
+    premise: Graph,
+    conclusion: Graph,
+    steps: Vec<InferenceRule>,
+}
+
+impl Lemma {
+    /// proves a lemma by applying `steps` to `premise`; whatever is left is the conclusion
+    #[instrument]
+    pub fn new(name: &str, premise: Graph, steps: Vec<InferenceRule>) -> ProofResult<Self> {
+        if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '=') {
+            Err(ProofError::RuleParseError(format!(
+                "{} is not a valid lemma name",
+                name
+            )))?
+        }
+
+        let mut conclusion = premise.clone();
+        for step in &steps {
+            Action::apply_actions(step.gen_actions(&conclusion)?, &mut conclusion)?;
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            premise,
+            conclusion,
+            steps,
+        })
+    }
+
+    /// errors unless every step of the lemma's proof is allowed by `rules`
+    #[instrument(skip(self))]
+    pub fn check_rules(&self, rules: &RuleSet) -> ProofResult<()> {
+        let mut graph = self.premise.clone();
+        for step in &self.steps {
+            Action::apply_actions(step.gen_actions_with_rules(&graph, rules)?, &mut graph)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn premise(&self) -> &Graph {
+        &self.premise
+    }
+
+    pub fn conclusion(&self) -> &Graph {
+        &self.conclusion
+    }
+
+    pub fn steps(&self) -> &[InferenceRule] {
+        &self.steps
+    }
+}
+
+/// the lemmas known to a proof, by name
+#[derive(Debug, Clone, Default)]
+pub struct LemmaLibrary {
+    lemmas: LinkedHashMap<String, Arc<Lemma>>,
+}
+
+impl LemmaLibrary {
+    #[instrument]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds a lemma, returning the lemma it replaced if one had the same name
+    #[instrument(skip(self))]
+    pub fn insert(&mut self, lemma: Lemma) -> Option<Arc<Lemma>> {
+        self.lemmas.insert(lemma.name.clone(), Arc::new(lemma))
+    }
+
+    #[instrument(skip(self))]
+    pub fn remove(&mut self, name: &str) -> Option<Arc<Lemma>> {
+        self.lemmas.remove(name)
+    }
+
+    #[instrument(skip(self))]
+    pub fn get(&self, name: &str) -> Option<&Arc<Lemma>> {
+        self.lemmas.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<Lemma>> {
+        self.lemmas.values()
+    }
+
+    /// lets `registry` read back [`LemmaRule`]s that use the lemmas currently in the library;
+    /// lemmas added later need this to be called again
+    #[instrument(skip(self))]
+    pub fn register_all(&self, registry: &mut RuleRegistry) {
+        let lemmas = self.lemmas.clone();
+
+        registry.register("lemma", move |s| {
+            Ok(Box::new(LemmaRule::parse(s, &lemmas)?) as Box<dyn Rule>)
+        });
+    }
+}
+
+/// writes every lemma as
+///
+/// ```text
+/// lemma NAME
+/// premise KEY_STATE KEYED_GRAPH
+/// step RULE
+/// ...
+/// end
+/// ```
+///
+/// the key state is kept so that steps creating new subgraphs give them the same ids when the
+/// library is read back
+impl Display for LemmaLibrary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for lemma in self.lemmas.values() {
+            writeln!(f, "lemma {}", lemma.name)?;
+            writeln!(
+                f,
+                "premise {} {}",
+                lemma.premise.key_state(),
+                transform_graph_into_keyed_string(&lemma.premise, lemma.premise.root_id())
+                    .map_err(|_| std::fmt::Error)?
+            )?;
+
+            for step in &lemma.steps {
+                writeln!(f, "step {}", step)?;
+            }
+
+            writeln!(f, "end")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for LemmaLibrary {
+    type Err = ProofError;
+
+    /// reads back a library written by its [`Display`] implementation; blank lines and lines
+    /// starting with `#` are skipped
+    #[instrument]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut library = Self::new();
+
+        let mut lines = s
+            .lines()
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty() && !v.starts_with('#'));
+
+        while let Some(line) = lines.next() {
+            let name = line
+                .strip_prefix("lemma ")
+                .ok_or_else(|| ProofError::RuleParseError(format!("expected a lemma: {}", line)))?
+                .trim();
+
+            let (key_state, premise) = lines
+                .next()
+                .and_then(|v| v.strip_prefix("premise "))
+                .and_then(|v| v.trim().split_once(' '))
+                .ok_or_else(|| {
+                    ProofError::RuleParseError(format!("lemma {} is missing its premise", name))
+                })?;
+            let key_state = key_state.parse::<u64>().map_err(|_| {
+                ProofError::RuleParseError(format!("{} is not a valid key state", key_state))
+            })?;
+            let premise = Graph::try_from(premise.trim())?.with_key_state(key_state);
+
+            let mut steps = Vec::new();
+            loop {
+                match lines.next() {
+                    Some("end") => break,
+                    Some(v) => steps.push(InferenceRule::from_str(
+                        v.strip_prefix("step ").ok_or_else(|| {
+                            ProofError::RuleParseError(format!("expected a step: {}", v))
+                        })?,
+                    )?),
+                    None => Err(ProofError::RuleParseError(format!(
+                        "lemma {} is missing its end",
+                        name
+                    )))?,
+                }
+            }
+
+            library.insert(Lemma::new(name, premise, steps)?);
+        }
+
+        Ok(library)
+    }
+}
+
+/// replaces an instance of a lemma's premise with the matching instance of its conclusion
+#[derive(Debug, Clone)]
+pub struct LemmaRule {
+    pub lemma: Arc<Lemma>,
+
+    /// the area holding the instance of the premise; needs to be on an even level
+    pub area: GraphKey,
+
+    /// the atoms of the instance
+    pub atoms: Vec<Arc<Atom>>,
+
+    /// the subgraphs of the instance
+    pub subgraphs: Vec<GraphKey>,
+
+    /// what the atoms of the lemma stand for; atoms without a substitute stand for themselves
+    pub substitution: Substitution,
+}
+
+impl LemmaRule {
+    /// reads a rule written by [`Rule::serialize`], looking its lemma up in `lemmas`
+    #[instrument(skip(lemmas))]
+    fn parse(s: &str, lemmas: &LinkedHashMap<String, Arc<Lemma>>) -> ProofResult<Self> {
+        let mut words = s.split_whitespace();
+        if words.next() != Some("lemma") {
+            Err(ProofError::MismatchedRule("lemma"))?
+        }
+
+        let fields = words
+            .map(|w| {
+                w.split_once('=')
+                    .ok_or_else(|| ProofError::RuleParseError(format!("{} is not a field", w)))
+            })
+            .collect::<ProofResult<Vec<_>>>()?;
+
+        let field = |k: &str| {
+            fields
+                .iter()
+                .find(|(n, _)| *n == k)
+                .map(|(_, v)| *v)
+                .ok_or_else(|| ProofError::RuleParseError(format!("lemma is missing {}", k)))
+        };
+        let key = |v: &str| {
+            GraphKey::try_from_str(v)
+                .map_err(|_| ProofError::RuleParseError(format!("{} is not a valid id", v)))
+        };
+
+        let name = field("name")?;
+
+        Ok(Self {
+            lemma: lemmas
+                .get(name)
+                .cloned()
+                .ok_or_else(|| ProofError::UnknownLemma(name.to_string()))?,
+            area: key(field("area")?)?,
+            atoms: field("atoms")?
+                .split(',')
+                .filter(|v| !v.is_empty())
+                .map(|v| Ok(Arc::new(Atom::from(unescape_field(v)?))))
+                .collect::<ProofResult<_>>()?,
+            subgraphs: field("subgraphs")?
+                .split(',')
+                .filter(|v| !v.is_empty())
+                .map(key)
+                .collect::<ProofResult<_>>()?,
+            substitution: fields
+                .iter()
+                .filter_map(|(n, v)| Some((n.strip_prefix("with.")?, v)))
+                .map(|(a, v)| {
+                    Ok((
+                        Atom::from(unescape_field(a)?),
+                        Graph::try_from(unescape_field(v)?.as_str())?,
+                    ))
+                })
+                .collect::<ProofResult<_>>()?,
+        })
+    }
+
+    /// works out the primitive steps and the canonical form of the graph they lead to, when
+    /// the substitution only renames atoms
+    #[instrument]
+    fn expand_steps(&self, graph: &Graph) -> ProofResult<(Vec<InferenceRule>, String)> {
+        let renaming = self
+            .substitution
+            .iter()
+            .map(|(a, g)| {
+                let root = g.root_id();
+
+                match (
+                    g.atoms_of(root)?.iter().collect_vec().as_slice(),
+                    g.subgraphs_of(root)?.is_empty(),
+                ) {
+                    ([b], true) => Ok((a.clone(), (*b).clone())),
+                    _ => Err(ProofError::NotExpandable(self.to_string())),
+                }
+            })
+            .collect::<ProofResult<HashMap<_, _>>>()?;
+
+        let mut lemma_scratch = self.lemma.premise.clone();
+        let mut scratch = graph.clone();
+
+        // the premise's root is the area, and its subgraphs are found among the selection
+        let mut keys = HashMap::from([(*lemma_scratch.root_id(), self.area)]);
+        let mut taken = graph
+            .subgraphs_of(&self.area)?
+            .iter()
+            .filter(|k| !self.subgraphs.contains(k))
+            .cloned()
+            .collect();
+        self.match_subgraphs(&lemma_scratch, &scratch, &mut keys, &mut taken)?;
+
+        let mut steps = Vec::new();
+        for step in &self.lemma.steps {
+            let translated = self.translate(step, &keys, &renaming)?;
+
+            Action::apply_actions(step.gen_actions(&lemma_scratch)?, &mut lemma_scratch)?;
+            Action::apply_actions(translated.gen_actions(&scratch)?, &mut scratch)?;
+            steps.push(translated);
+
+            self.match_subgraphs(&lemma_scratch, &scratch, &mut keys, &mut taken)?;
+        }
+
+        Ok((
+            steps,
+            transform_graph_into_canonical_string(&scratch, scratch.root_id())?,
+        ))
+    }
+
+    /// pairs up the subgraphs of the lemma with the subgraphs of `graph` they correspond to,
+    /// going down from the subgraphs already paired up
+    #[instrument(skip(keys, taken))]
+    fn match_subgraphs(
+        &self,
+        lemma_graph: &Graph,
+        graph: &Graph,
+        keys: &mut HashMap<GraphKey, GraphKey>,
+        taken: &mut HashSet<GraphKey>,
+    ) -> GraphResult<()> {
+        let mut queue = VecDeque::from([*lemma_graph.root_id()]);
+
+        while let Some(lk) = queue.pop_front() {
+            let Some(gk) = keys.get(&lk).copied() else {
+                continue;
+            };
+
+            for lc in lemma_graph.subgraphs_of(&lk)? {
+                if !keys.contains_key(lc) {
+                    let wanted = transform_substituted_graph_into_canonical_string(
+                        lemma_graph,
+                        lc,
+                        &self.substitution,
+                    )?;
+
+                    for gc in graph.subgraphs_of(&gk)? {
+                        if !taken.contains(gc)
+                            && transform_graph_into_canonical_string(graph, gc)? == wanted
+                        {
+                            keys.insert(*lc, *gc);
+                            taken.insert(*gc);
+                            break;
+                        }
+                    }
+                }
+
+                queue.push_back(*lc);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// the step of the lemma's proof, but acting on `graph` instead of the premise
+    #[instrument(skip(keys, renaming))]
+    fn translate(
+        &self,
+        step: &InferenceRule,
+        keys: &HashMap<GraphKey, GraphKey>,
+        renaming: &HashMap<Atom, Arc<Atom>>,
+    ) -> ProofResult<InferenceRule> {
+        let key = |k: &GraphKey| {
+            keys.get(k)
+                .copied()
+                .ok_or_else(|| ProofError::NotExpandable(self.to_string()))
+        };
+        let keys = |v: &[GraphKey]| v.iter().map(key).collect::<ProofResult<Vec<_>>>();
+        let atom = |a: &Arc<Atom>| renaming.get(&**a).cloned().unwrap_or_else(|| a.clone());
+        let atoms = |v: &[Arc<Atom>]| v.iter().map(atom).collect::<Vec<_>>();
+
+        Ok(match step {
+            InferenceRule::DoubleCutDraw(DoubleCutDraw {
+                target,
+                target_atoms,
+                target_subgraphs,
+            }) => InferenceRule::DoubleCutDraw(DoubleCutDraw {
+                target: key(target)?,
+                target_atoms: atoms(target_atoms),
+                target_subgraphs: keys(target_subgraphs)?,
+            }),
+            InferenceRule::DoubleCutErase(DoubleCutErase { target }) => {
+                InferenceRule::DoubleCutErase(DoubleCutErase {
+                    target: key(target)?,
+                })
+            }
+            InferenceRule::Insertion(Insertion {
+                target,
+                new_content,
+            }) => {
+                let content = Graph::try_from(new_content.as_str())?;
+
+                InferenceRule::Insertion(Insertion {
+                    target: key(target)?,
+                    new_content: transform_substituted_graph_into_string(
+                        &content,
+                        content.root_id(),
+                        &self.substitution,
+                    )?,
+                })
+            }
+            InferenceRule::Erasure(Erasure {
+                target_subgraphs,
+                target_atoms,
+            }) => InferenceRule::Erasure(Erasure {
+                target_subgraphs: keys(target_subgraphs)?,
+                target_atoms: target_atoms
+                    .iter()
+                    .map(|(k, a)| Ok((key(k)?, atom(a))))
+                    .collect::<ProofResult<_>>()?,
+            }),
+            InferenceRule::Iteration(Iteration {
+                backwards,
+                parent,
+                parent_atoms,
+                parent_subgraphs,
+                target,
+            }) => InferenceRule::Iteration(Iteration {
+                backwards: *backwards,
+                parent: key(parent)?,
+                parent_atoms: atoms(parent_atoms),
+                parent_subgraphs: keys(parent_subgraphs)?,
+                target: key(target)?,
+            }),
+        })
+    }
+}
+
+impl Rule for LemmaRule {
+    fn name(&self) -> &str {
+        "lemma"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "Apply lemma {} to [{}] in {}",
+            self.lemma.name,
+            list_selection(&self.atoms, &self.subgraphs),
+            self.area
+        )
+    }
+
+    /// deletes the selection and puts the substituted conclusion in its place
+    fn gen_actions(&self, graph: &Graph) -> ProofResult<LinkedList<Action>> {
+        let level = graph.level_of(&self.area)?;
+        if Parity::of(level) != Parity::Even {
+            Err(ProofError::WrongParity {
+                target: self.area,
+                expected: Parity::Even,
+                level,
+            })?
+        }
+
+        check_selection(graph, &self.area, &self.atoms, &self.subgraphs)?;
+
+        let premise = &self.lemma.premise;
+        if canonical_selection(graph, &self.atoms, &self.subgraphs)?
+            != transform_substituted_graph_into_canonical_string(
+                premise,
+                premise.root_id(),
+                &self.substitution,
+            )?
+        {
+            Err(ProofError::NotAnInstance {
+                area: self.area,
+                lemma: self.lemma.name.clone(),
+            })?
+        }
+
+        let mut ans = gen_actions_deleting_subgraphs(graph, &self.subgraphs)?;
+        ans.extend(self.atoms.iter().map(|a| Action::DeleteAtom {
+            target: GraphTarget::Exists(self.area),
+            atom: (**a).clone(),
+        }));
+
+        // the conclusion goes into the area as it is once the selection is gone
+        let mut scratch = graph.clone();
+        Action::apply_actions(ans.clone(), &mut scratch)?;
+
+        let conclusion = &self.lemma.conclusion;
+        ans.extend(parse_graph_string_into_actions(
+            &scratch,
+            &transform_substituted_graph_into_string(
+                conclusion,
+                conclusion.root_id(),
+                &self.substitution,
+            )?,
+            self.area,
+        )?);
+
+        Ok(ans)
+    }
+
+    /// the lemma can only be used where its proof could have been given instead
+    fn gen_actions_with_rules(
+        &self,
+        graph: &Graph,
+        rules: &RuleSet,
+    ) -> ProofResult<LinkedList<Action>> {
+        rules.check_enabled(self.name())?;
+        self.lemma.check_rules(rules)?;
+        self.gen_actions(graph)
+    }
+
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    /// the steps of the lemma's proof, moved over to `graph`
+    ///
+    /// only works when every substitute is a single atom, since otherwise the steps would have
+    /// to be rewritten to act on whole graphs
+    fn expand(&self, graph: &Graph) -> ProofResult<Vec<InferenceRule>> {
+        let mut scratch = graph.clone();
+        Action::apply_actions(self.gen_actions(graph)?, &mut scratch)?;
+        let expected = transform_graph_into_canonical_string(&scratch, scratch.root_id())?;
+
+        match self.expand_steps(graph) {
+            Ok((steps, result)) if result == expected => Ok(steps),
+            _ => Err(ProofError::NotExpandable(self.to_string())),
+        }
+    }
+}
+
+/// writes the rule as `lemma name=N area=K atoms=... subgraphs=... with.A=[...] ...`
+impl Display for LemmaRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "lemma name={} area={} atoms={} subgraphs={}",
+            self.lemma.name,
+            self.area,
+            self.atoms
+                .iter()
+                .map(|a| escape_field(&a.to_string(), ","))
+                .join(","),
+            self.subgraphs.iter().join(",")
+        )?;
+
+        for (a, g) in self
+            .substitution
+            .iter()
+            .sorted_by_key(|(a, _)| a.to_string())
+        {
+            write!(
+                f,
+                " with.{}={}",
+                escape_field(&a.to_string(), "="),
+                escape_field(
+                    &transform_graph_into_string(g, g.root_id()).map_err(|_| std::fmt::Error)?,
+                    ""
+                )
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use super::{action::Action, error::ProofResult, rule::Rule, rule_set::RuleSet, Proof};
+use crate::graph::{transform_graph_into_canonical_string, Graph, GraphKey};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use tracing::instrument;
+
+/// a proof with its unneeded steps taken out
+#[derive(Debug, Clone)]
+pub struct Minimized {
+    pub proof: Proof,
+
+    /// the positions of the removed steps in the original proof
+    pub removed: Vec<usize>,
+}
+
+/// removes steps from `proof` for as long as what's left still proves the same conclusion
+///
+/// single steps are tried first, then pairs, so that a double cut drawn and later erased again
+/// goes away. every candidate is checked by replaying it, so the result is always a valid proof
+#[instrument]
+pub fn minimize(proof: &Proof) -> ProofResult<Minimized> {
+    let trace = Trace::of(proof)?;
+    let n = proof.steps().len();
+
+    let mut kept = vec![true; n];
+    let mut best = proof.steps().to_vec();
+
+    loop {
+        let mut changed = false;
+
+        for i in (0..n).rev() {
+            if !kept[i] {
+                continue;
+            }
+
+            kept[i] = false;
+
+            match trace.replay(proof, &kept) {
+                Some(steps) => {
+                    best = steps;
+                    changed = true;
+                }
+                None => kept[i] = true,
+            }
+        }
+
+        for j in (0..n).rev() {
+            for i in (0..j).rev() {
+                if !kept[i] || !kept[j] {
+                    continue;
+                }
+
+                kept[i] = false;
+                kept[j] = false;
+
+                match trace.replay(proof, &kept) {
+                    Some(steps) => {
+                        best = steps;
+                        changed = true;
+                    }
+                    None => {
+                        kept[i] = true;
+                        kept[j] = true;
+                    }
+                }
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    Ok(Minimized {
+        proof: Proof::new(proof.premise().clone(), best).with_rule_set(proof.rule_set().clone()),
+        removed: (0..n).filter(|i| !kept[*i]).collect(),
+    })
+}
+
+/// what replaying the original proof looks like
+struct Trace {
+    /// the subgraphs each step added, in the order they were added
+    added: Vec<Vec<GraphKey>>,
+
+    conclusion: String,
+}
+
+impl Trace {
+    fn of(proof: &Proof) -> ProofResult<Self> {
+        let mut graph = proof.premise().clone();
+        let mut added = Vec::new();
+
+        for step in proof.steps() {
+            added.push(apply(step.as_ref(), proof.rule_set(), &mut graph)?);
+        }
+
+        Ok(Self {
+            added,
+            conclusion: transform_graph_into_canonical_string(&graph, graph.root_id())?,
+        })
+    }
+
+    /// replays only the kept steps, returning them if they still reach the conclusion
+    ///
+    /// subgraphs can get different ids once earlier steps are gone, so the ids used by later
+    /// steps are changed to match
+    fn replay(&self, proof: &Proof, kept: &[bool]) -> Option<Vec<Arc<dyn Rule>>> {
+        let mut graph = proof.premise().clone();
+        let mut keys = HashMap::new();
+        let mut steps = Vec::new();
+
+        for (i, step) in proof.steps().iter().enumerate().filter(|(i, _)| kept[*i]) {
+            let step = step
+                .map_keys(&|k| *keys.get(&k).unwrap_or(&k))
+                .unwrap_or_else(|| step.clone());
+            let added = apply(step.as_ref(), proof.rule_set(), &mut graph).ok()?;
+
+            if added.len() == self.added[i].len() {
+                keys.extend(self.added[i].iter().cloned().zip(added));
+            }
+
+            steps.push(step);
+        }
+
+        (transform_graph_into_canonical_string(&graph, graph.root_id()).ok()? == self.conclusion)
+            .then_some(steps)
+    }
+}
+
+/// applies `step` as `rules` allows it, returning the subgraphs it added
+#[instrument(skip(graph))]
+fn apply(step: &dyn Rule, rules: &RuleSet, graph: &mut Graph) -> ProofResult<Vec<GraphKey>> {
+    let before = graph.subgraph_keys().cloned().collect::<HashSet<_>>();
+
+    Action::apply_actions(step.gen_actions_with_rules(graph, rules)?, graph)?;
+
+    Ok(graph
+        .subgraph_keys()
+        .filter(|k| !before.contains(*k))
+        .cloned()
+        .collect())
+}
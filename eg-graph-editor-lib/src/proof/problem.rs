@@ -0,0 +1,253 @@
+use super::{
+    action::Action,
+    error::{ProofError, ProofResult},
+    rule_set::RuleSet,
+    Proof,
+};
+use crate::graph::{
+    error::GraphResult, parse_graph_string_into_actions, transform_graph_into_canonical_string,
+    transform_graph_into_string, Graph,
+};
+use std::{fmt::Display, str::FromStr};
+use tracing::instrument;
+
+/// what a problem asks to be proven
+#[derive(Debug, Clone)]
+pub enum Goal {
+    /// a graph the sheet should end up as
+    Graph(Graph),
+
+    /// an empty cut on the sheet
+    Contradiction,
+}
+
+/// an exercise: premises to start from and a goal to reach
+#[derive(Debug, Clone)]
+pub struct Problem {
+    name: String,
+    premises: Vec<Graph>,
+    goal: Goal,
+    rule_set: RuleSet,
+}
+
+impl Problem {
+    #[instrument]
+    pub fn new(name: &str, premises: Vec<Graph>, goal: Goal) -> ProofResult<Self> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            Err(ProofError::ProblemParseError(format!(
+                "{} is not a valid problem name",
+                name
+            )))?
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            premises,
+            goal,
+            rule_set: RuleSet::default(),
+        })
+    }
+
+    /// the same problem, to be proven with `rule_set` instead of the default
+    #[instrument]
+    pub fn with_rule_set(mut self, rule_set: RuleSet) -> Self {
+        self.rule_set = rule_set;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn premises(&self) -> &[Graph] {
+        &self.premises
+    }
+
+    pub fn goal(&self) -> &Goal {
+        &self.goal
+    }
+
+    pub fn rule_set(&self) -> &RuleSet {
+        &self.rule_set
+    }
+
+    /// every premise together on the sheet of assertion
+    #[instrument]
+    pub fn sheet(&self) -> ProofResult<Graph> {
+        let mut sheet = Graph::new();
+        let root = *sheet.root_id();
+
+        for premise in &self.premises {
+            let actions = parse_graph_string_into_actions(
+                &sheet,
+                &transform_graph_into_string(premise, premise.root_id())?,
+                root,
+            )?;
+
+            Action::apply_actions(actions, &mut sheet)?;
+        }
+
+        Ok(sheet)
+    }
+
+    /// a proof with no steps yet, starting from the sheet and using the problem's rule set
+    #[instrument]
+    pub fn start(&self) -> ProofResult<Proof> {
+        Ok(Proof::new(self.sheet()?, vec![]).with_rule_set(self.rule_set.clone()))
+    }
+
+    /// whether `graph` is the goal, up to ids
+    #[instrument]
+    pub fn is_reached_by(&self, graph: &Graph) -> GraphResult<bool> {
+        match &self.goal {
+            Goal::Graph(goal) => Ok(
+                transform_graph_into_canonical_string(graph, graph.root_id())?
+                    == transform_graph_into_canonical_string(goal, goal.root_id())?,
+            ),
+            Goal::Contradiction => {
+                for k in graph.subgraphs_of(graph.root_id())? {
+                    if graph.atoms_of(k)?.is_empty() && graph.subgraphs_of(k)?.is_empty() {
+                        return Ok(true);
+                    }
+                }
+
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// reads every problem in a file written as
+///
+/// ```text
+/// problem NAME
+/// premise GRAPH
+/// ...
+/// goal GRAPH | goal contradiction
+/// rules RULESET
+/// end
+/// ```
+///
+/// the `rules` line is optional and is read by [`RuleSet`]'s `FromStr`
+///
+/// blank lines and lines starting with `#` are skipped
+#[instrument]
+pub fn parse_problems(s: &str) -> ProofResult<Vec<Problem>> {
+    let mut ans = Vec::new();
+    let mut block = Vec::new();
+
+    for line in s
+        .lines()
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty() && !v.starts_with('#'))
+    {
+        block.push(line);
+
+        if line == "end" {
+            ans.push(Problem::from_str(&block.join("\n"))?);
+            block.clear();
+        }
+    }
+
+    if !block.is_empty() {
+        Err(ProofError::ProblemParseError(format!(
+            "{} is missing its end",
+            block[0]
+        )))?
+    }
+
+    Ok(ans)
+}
+
+/// writes problems in the format [`parse_problems`] reads
+#[instrument(skip(problems))]
+pub fn write_problems<'a>(problems: impl IntoIterator<Item = &'a Problem>) -> String {
+    problems.into_iter().map(|v| v.to_string()).collect()
+}
+
+/// graphs are written in canonical form, so the same problem is always written the same way
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "problem {}", self.name)?;
+
+        for premise in &self.premises {
+            writeln!(
+                f,
+                "premise {}",
+                transform_graph_into_canonical_string(premise, premise.root_id())
+                    .map_err(|_| std::fmt::Error)?
+            )?;
+        }
+
+        match &self.goal {
+            Goal::Graph(goal) => writeln!(
+                f,
+                "goal {}",
+                transform_graph_into_canonical_string(goal, goal.root_id())
+                    .map_err(|_| std::fmt::Error)?
+            )?,
+            Goal::Contradiction => writeln!(f, "goal contradiction")?,
+        }
+
+        if self.rule_set != RuleSet::default() {
+            writeln!(f, "rules {}", self.rule_set)?;
+        }
+
+        writeln!(f, "end")
+    }
+}
+
+impl FromStr for Problem {
+    type Err = ProofError;
+
+    /// reads a single problem, as written by its [`Display`] implementation
+    #[instrument]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = None;
+        let mut premises = Vec::new();
+        let mut goal = None;
+        let mut rule_set = None;
+        let mut ended = false;
+
+        for line in s
+            .lines()
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty() && !v.starts_with('#'))
+        {
+            let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let rest = rest.trim();
+
+            match word {
+                _ if ended => Err(ProofError::ProblemParseError(format!(
+                    "unexpected {} after end",
+                    line
+                )))?,
+                "problem" if name.is_none() => name = Some(rest),
+                "premise" => premises.push(Graph::try_from(rest)?),
+                "goal" if goal.is_none() => {
+                    goal = Some(match rest {
+                        "contradiction" => Goal::Contradiction,
+                        v => Goal::Graph(Graph::try_from(v)?),
+                    })
+                }
+                "rules" if rule_set.is_none() => rule_set = Some(RuleSet::from_str(rest)?),
+                "end" => ended = true,
+                _ => Err(ProofError::ProblemParseError(format!(
+                    "unexpected {}",
+                    line
+                )))?,
+            }
+        }
+
+        let name = name.ok_or_else(|| ProofError::ProblemParseError("missing name".to_string()))?;
+
+        Ok(Problem::new(
+            name,
+            premises,
+            goal.ok_or_else(|| {
+                ProofError::ProblemParseError(format!("problem {} is missing its goal", name))
+            })?,
+        )?
+        .with_rule_set(rule_set.unwrap_or_default()))
+    }
+}
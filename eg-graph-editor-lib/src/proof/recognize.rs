@@ -0,0 +1,220 @@
+use super::{
+    action::Action,
+    error::{Element, Parity, ProofResult},
+    inference_rule::{DoubleCutDraw, Erasure, InferenceRule, Insertion, Iteration},
+    rule::Rule,
+    rule_set::RuleSet,
+};
+use crate::{
+    atom::Atom,
+    graph::{
+        error::GraphResult, escape_atom, transform_graph_into_canonical_string, Graph, GraphKey,
+    },
+};
+use itertools::Itertools;
+use std::{collections::VecDeque, sync::Arc};
+use tracing::instrument;
+
+/// finds a single rule application that `rules` allows and turns `before` into `after`
+///
+/// `after` is compared by structure alone, so it doesn't need to share any ids with `before`.
+/// returns `None` if no single step does it
+#[instrument(skip(before, after))]
+pub fn recognize(
+    before: &Graph,
+    after: &Graph,
+    rules: &RuleSet,
+) -> ProofResult<Option<InferenceRule>> {
+    let wanted = transform_graph_into_canonical_string(after, after.root_id())?;
+
+    // whatever changes several elements at once is worked out from the difference, so that only
+    // the single element applications need to be listed
+    let candidates = difference_candidates(before, after)?.into_iter().chain(
+        InferenceRule::applicable_bounded(before, rules)
+            .filter(|v| !matches!(v, InferenceRule::Insertion(Insertion { .. }))),
+    );
+
+    for rule in candidates {
+        let mut graph = before.clone();
+
+        let Ok(actions) = rule.gen_actions_with_rules(&graph, rules) else {
+            continue;
+        };
+        if Action::apply_actions(actions, &mut graph).is_err() {
+            continue;
+        }
+
+        if transform_graph_into_canonical_string(&graph, graph.root_id())? == wanted {
+            return Ok(Some(rule));
+        }
+    }
+
+    Ok(None)
+}
+
+/// the rule applications that could explain how some area of `before` differs from an area of
+/// `after` on the same level: what was inserted into it, erased or deiterated from it, iterated
+/// into it, or had a double cut drawn around it
+#[instrument(skip(before, after))]
+fn difference_candidates(before: &Graph, after: &Graph) -> GraphResult<Vec<InferenceRule>> {
+    let before_areas = areas(before)?;
+    let after_areas = areas(after)?;
+
+    let mut ans = Vec::new();
+
+    for area in &before_areas {
+        let mut ancestors = vec![area.key];
+        while let Some(parent) = ancestors.last().and_then(|v| before.parent_of(v).ok()) {
+            ancestors.push(*parent);
+        }
+
+        for after_area in after_areas.iter().filter(|v| v.level == area.level) {
+            let (gone, added) = difference(&area.contents, &after_area.contents);
+            let (atoms, subgraphs) = split(&gone);
+
+            if gone.is_empty() && !added.is_empty() {
+                for parent in before_areas
+                    .iter()
+                    .filter(|v| ancestors[1..].contains(&v.key))
+                {
+                    let Some(copies) = find(&parent.contents, &added) else {
+                        continue;
+                    };
+                    let (parent_atoms, parent_subgraphs) = split(&copies);
+
+                    ans.push(InferenceRule::Iteration(Iteration {
+                        backwards: false,
+                        parent: parent.key,
+                        parent_atoms,
+                        parent_subgraphs,
+                        target: area.key,
+                    }));
+                }
+
+                // anything iterated could also have been inserted, but iterating says more
+                if Parity::of(area.level) == Parity::Odd {
+                    ans.push(InferenceRule::Insertion(Insertion {
+                        target: area.key,
+                        new_content: format!("[{}]", added.iter().join(",")),
+                    }));
+                }
+            }
+
+            if !gone.is_empty() && added.is_empty() {
+                if Parity::of(area.level) == Parity::Even {
+                    ans.push(InferenceRule::Erasure(Erasure {
+                        target_subgraphs: subgraphs.clone(),
+                        target_atoms: atoms.iter().map(|a| (area.key, a.clone())).collect(),
+                    }));
+                }
+
+                for target in &ancestors {
+                    ans.push(InferenceRule::Iteration(Iteration {
+                        backwards: true,
+                        parent: area.key,
+                        parent_atoms: atoms.clone(),
+                        parent_subgraphs: subgraphs.clone(),
+                        target: *target,
+                    }));
+                }
+            }
+
+            if added.len() == 1 {
+                ans.push(InferenceRule::DoubleCutDraw(DoubleCutDraw {
+                    target: area.key,
+                    target_atoms: atoms,
+                    target_subgraphs: subgraphs,
+                }));
+            }
+        }
+    }
+
+    Ok(ans)
+}
+
+/// what `before` has that `after` doesn't, and the canonical strings of what `after` has that
+/// `before` doesn't
+fn difference<'a>(
+    before: &[(String, Element)],
+    after: &'a [(String, Element)],
+) -> (Vec<Element>, Vec<&'a str>) {
+    let mut added = after.iter().map(|(v, _)| v.as_str()).collect_vec();
+    let mut gone = Vec::new();
+
+    for (v, element) in before {
+        match added.iter().position(|e| e == v) {
+            Some(i) => {
+                added.remove(i);
+            }
+            None => gone.push(element.clone()),
+        }
+    }
+
+    (gone, added)
+}
+
+/// an element of `contents` for every one of `wanted`, or `None` if some are missing
+fn find(contents: &[(String, Element)], wanted: &[&str]) -> Option<Vec<Element>> {
+    let mut left = contents.iter().collect_vec();
+
+    wanted
+        .iter()
+        .map(|v| {
+            let i = left.iter().position(|(e, _)| e == v)?;
+            Some(left.remove(i).1.clone())
+        })
+        .collect()
+}
+
+/// the atoms and the subgraphs among `elements`
+fn split(elements: &[Element]) -> (Vec<Arc<Atom>>, Vec<GraphKey>) {
+    let mut atoms = Vec::new();
+    let mut subgraphs = Vec::new();
+
+    for element in elements {
+        match element {
+            Element::Atom(a) => atoms.push(a.clone()),
+            Element::Subgraph(k) => subgraphs.push(*k),
+        }
+    }
+
+    (atoms, subgraphs)
+}
+
+/// an area of a graph, with the canonical string of everything it directly contains
+struct Area {
+    key: GraphKey,
+    level: usize,
+    contents: Vec<(String, Element)>,
+}
+
+/// every area of `graph`
+#[instrument(skip(graph))]
+fn areas(graph: &Graph) -> GraphResult<Vec<Area>> {
+    let mut ans = Vec::new();
+
+    let mut queue = VecDeque::from([*graph.root_id()]);
+    while let Some(id) = queue.pop_front() {
+        let mut contents = graph
+            .atoms_of(&id)?
+            .iter()
+            .map(|a| (escape_atom(&a.to_string()), Element::Atom(a.clone())))
+            .collect_vec();
+
+        for k in graph.subgraphs_of(&id)? {
+            contents.push((
+                transform_graph_into_canonical_string(graph, k)?,
+                Element::Subgraph(*k),
+            ));
+            queue.push_back(*k);
+        }
+
+        ans.push(Area {
+            key: id,
+            level: graph.level_of(&id)?,
+            contents,
+        });
+    }
+
+    Ok(ans)
+}
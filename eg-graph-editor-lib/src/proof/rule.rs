@@ -0,0 +1,202 @@
+use super::{
+    action::Action,
+    derived_rule::DerivedRule,
+    error::{ProofError, ProofResult},
+    inference_rule::{DoubleCutDraw, DoubleCutErase, Erasure, InferenceRule, Insertion, Iteration},
+    rule_set::RuleSet,
+};
+use crate::graph::{Graph, GraphKey};
+use std::{
+    collections::{HashMap, LinkedList},
+    fmt::Debug,
+    str::FromStr,
+    sync::Arc,
+};
+use tracing::instrument;
+
+/// a single application of an inference rule to a graph
+///
+/// each built in rule is a type of its own, gathered in [`InferenceRule`]; anything else, like
+/// derived rules or restricted versions of the built in ones, can implement this and be added to
+/// a [`RuleRegistry`], and can then be used as a step of a [`super::Proof`]
+pub trait Rule: Debug + Send + Sync {
+    /// the name the rule is registered under; also the first word of [`Rule::serialize`]
+    fn name(&self) -> &str;
+
+    /// a short human readable description of what applying the rule does
+    fn describe(&self) -> String;
+
+    /// generates the actions that apply the rule to `graph`
+    fn gen_actions(&self, graph: &Graph) -> ProofResult<LinkedList<Action>>;
+
+    /// generates the actions that apply the rule to `graph`, refusing whatever `rules` doesn't
+    /// allow; unless overridden, this only checks that the rule isn't disabled
+    fn gen_actions_with_rules(
+        &self,
+        graph: &Graph,
+        rules: &RuleSet,
+    ) -> ProofResult<LinkedList<Action>> {
+        rules.check_enabled(self.name())?;
+        self.gen_actions(graph)
+    }
+
+    /// the rule in a form that [`RuleRegistry::parse`] can read back
+    fn serialize(&self) -> String;
+
+    /// checks whether the rule can be applied to `graph`
+    fn validate(&self, graph: &Graph) -> ProofResult<()> {
+        self.gen_actions(graph).map(|_| ())
+    }
+
+    /// the primitive steps that applying the rule to `graph` amounts to, so that proofs using
+    /// the rule can be checked with only the built in rules
+    fn expand(&self, graph: &Graph) -> ProofResult<Vec<InferenceRule>> {
+        let _ = graph;
+        Err(ProofError::NotExpandable(self.name().to_string()))
+    }
+
+    /// the same rule with every subgraph id passed through `f`, so that a step can follow its
+    /// subgraphs when they get different ids; `None` if the rule has no ids to change
+    fn map_keys(&self, f: &dyn Fn(GraphKey) -> GraphKey) -> Option<Arc<dyn Rule>> {
+        let _ = f;
+        None
+    }
+}
+
+pub type RuleParser = Arc<dyn Fn(&str) -> ProofResult<Box<dyn Rule>> + Send + Sync>;
+
+/// looks up rules by name so that serialized rules can be read back
+#[derive(Clone)]
+pub struct RuleRegistry {
+    parsers: HashMap<String, RuleParser>,
+}
+
+impl RuleRegistry {
+    /// a registry that doesn't know about any rules, not even the built in ones
+    #[instrument]
+    pub fn empty() -> Self {
+        Self {
+            parsers: Default::default(),
+        }
+    }
+
+    /// a registry that knows about every built in rule and every [`DerivedRule`]
+    #[instrument]
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+
+        registry.register("double_cut_draw", |s| {
+            Ok(Box::new(DoubleCutDraw::from_str(s)?))
+        });
+        registry.register("double_cut_erase", |s| {
+            Ok(Box::new(DoubleCutErase::from_str(s)?))
+        });
+        registry.register("insertion", |s| Ok(Box::new(Insertion::from_str(s)?)));
+        registry.register("erasure", |s| Ok(Box::new(Erasure::from_str(s)?)));
+        for name in ["iteration", "deiteration"] {
+            registry.register(name, |s| Ok(Box::new(Iteration::from_str(s)?)));
+        }
+
+        DerivedRule::register_all(&mut registry);
+
+        registry
+    }
+
+    /// adds a rule, replacing any rule previously registered with the same name
+    ///
+    /// the parser is given the whole serialized rule, including the name
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        parser: impl Fn(&str) -> ProofResult<Box<dyn Rule>> + Send + Sync + 'static,
+    ) {
+        self.parsers.insert(name.into(), Arc::new(parser));
+    }
+
+    #[instrument(skip(self))]
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.parsers.remove(name).is_some()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.parsers.keys().map(|v| v.as_str())
+    }
+
+    #[instrument(skip(self))]
+    pub fn contains(&self, name: &str) -> bool {
+        self.parsers.contains_key(name)
+    }
+
+    /// reads back a rule written by [`Rule::serialize`]
+    #[instrument(skip(self))]
+    pub fn parse(&self, serialized: &str) -> ProofResult<Box<dyn Rule>> {
+        let name = serialized
+            .split_whitespace()
+            .next()
+            .ok_or_else(|| ProofError::RuleParseError(serialized.to_string()))?;
+
+        let parser = self
+            .parsers
+            .get(name)
+            .ok_or_else(|| ProofError::UnknownRule(name.to_string()))?;
+
+        parser(serialized.trim())
+    }
+}
+
+impl Default for RuleRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for RuleRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.parsers.keys()).finish()
+    }
+}
+
+/// makes `value` safe to use as a field of a serialized rule, by writing `%`, whitespace and
+/// every character in `reserved` as `%XX` escapes of their UTF-8 bytes
+pub fn escape_field(value: &str, reserved: &str) -> String {
+    let mut ans = String::new();
+
+    for c in value.chars() {
+        if c == '%' || c.is_whitespace() || reserved.contains(c) {
+            let mut bytes = [0; 4];
+            for b in c.encode_utf8(&mut bytes).bytes() {
+                ans.push_str(&format!("%{:02X}", b));
+            }
+        } else {
+            ans.push(c);
+        }
+    }
+
+    ans
+}
+
+/// undoes [`escape_field`]
+pub fn unescape_field(value: &str) -> ProofResult<String> {
+    let error = || ProofError::RuleParseError(format!("{} is not escaped properly", value));
+
+    let mut bytes = Vec::new();
+    let mut rest = value.as_bytes();
+
+    while let Some((b, tail)) = rest.split_first() {
+        match b {
+            b'%' => {
+                let hex = tail.get(..2).ok_or_else(error)?;
+                let hex = std::str::from_utf8(hex).map_err(|_| error())?;
+
+                bytes.push(u8::from_str_radix(hex, 16).map_err(|_| error())?);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(*b);
+                rest = tail;
+            }
+        }
+    }
+
+    String::from_utf8(bytes).map_err(|_| error())
+}
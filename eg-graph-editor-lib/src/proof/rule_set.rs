@@ -0,0 +1,160 @@
+use super::error::{ProofError, ProofResult};
+use itertools::Itertools;
+use std::{collections::BTreeSet, fmt::Display, str::FromStr};
+use tracing::instrument;
+
+/// what double cuts may be drawn around or erased from around
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoubleCuts {
+    AroundAnything,
+
+    /// only empty double cuts; not how any of the usual presentations have it, but handy for
+    /// exercises
+    AroundNothing,
+}
+
+/// the version of the alpha rules that inference rules are checked against
+///
+/// erasure and insertion always go by whether an area is inside an even or odd number of cuts;
+/// for alpha graphs that is the same as it being a positive or negative context, so it isn't
+/// configurable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleSet {
+    pub double_cuts: DoubleCuts,
+
+    /// whether iteration and deiteration may work within one area, instead of only from an area
+    /// into the areas it encloses
+    pub iteration_within_area: bool,
+
+    /// names of the rules that can't be used at all, as given by [`super::rule::Rule::name`]
+    pub disabled: BTreeSet<String>,
+}
+
+impl RuleSet {
+    pub const PRESETS: [&'static str; 5] = [
+        "peirce",
+        "roberts",
+        "sowa",
+        "nested_iteration",
+        "empty_double_cuts",
+    ];
+
+    /// every rule as Peirce gives them: iteration into the same area or any area it encloses, and
+    /// double cuts around anything, as long as there's nothing between the two cuts
+    pub fn peirce() -> Self {
+        Self {
+            double_cuts: DoubleCuts::AroundAnything,
+            iteration_within_area: true,
+            disabled: BTreeSet::new(),
+        }
+    }
+
+    /// like [`RuleSet::peirce`], but iteration only goes into enclosed areas
+    pub fn nested_iteration() -> Self {
+        Self {
+            iteration_within_area: false,
+            ..Self::peirce()
+        }
+    }
+
+    /// like [`RuleSet::peirce`], but double cuts can only be drawn and erased when empty
+    pub fn empty_double_cuts() -> Self {
+        Self {
+            double_cuts: DoubleCuts::AroundNothing,
+            ..Self::peirce()
+        }
+    }
+
+    #[instrument]
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            // Roberts and Sowa give the alpha rules the same way Peirce does
+            "peirce" | "roberts" | "sowa" => Some(Self::peirce()),
+            "nested_iteration" => Some(Self::nested_iteration()),
+            "empty_double_cuts" => Some(Self::empty_double_cuts()),
+            _ => None,
+        }
+    }
+
+    /// the same rule set, with the named rule turned off
+    #[instrument]
+    pub fn without(mut self, rule: &str) -> Self {
+        self.disabled.insert(rule.to_string());
+        self
+    }
+
+    /// errors if the named rule is turned off
+    #[instrument]
+    pub fn check_enabled(&self, rule: &str) -> ProofResult<()> {
+        if self.disabled.contains(rule) {
+            Err(ProofError::RuleDisabled(rule.to_string()))?
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self::peirce()
+    }
+}
+
+/// writes every setting as `double_cuts=... iteration_within_area=... disabled=...`
+impl Display for RuleSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "double_cuts={} iteration_within_area={} disabled={}",
+            match self.double_cuts {
+                DoubleCuts::AroundAnything => "anything",
+                DoubleCuts::AroundNothing => "nothing",
+            },
+            self.iteration_within_area,
+            self.disabled.iter().join(",")
+        )
+    }
+}
+
+impl FromStr for RuleSet {
+    type Err = ProofError;
+
+    /// reads an optional preset name followed by settings that override it, as in
+    /// `nested_iteration disabled=insertion`
+    #[instrument]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace().peekable();
+        let error =
+            |v: &str| ProofError::RuleParseError(format!("{} is not a rule set setting", v));
+
+        let mut ans = match words.peek() {
+            Some(v) if !v.contains('=') => {
+                let ans = Self::preset(v)
+                    .ok_or_else(|| ProofError::RuleParseError(format!("no preset named {}", v)))?;
+                words.next();
+                ans
+            }
+            _ => Self::default(),
+        };
+
+        for word in words {
+            match word.split_once('=').ok_or_else(|| error(word))? {
+                ("double_cuts", "anything") => ans.double_cuts = DoubleCuts::AroundAnything,
+                ("double_cuts", "nothing") => ans.double_cuts = DoubleCuts::AroundNothing,
+                ("iteration_within_area", v) => {
+                    ans.iteration_within_area = v.parse().map_err(|_| error(word))?
+                }
+                ("disabled", v) => {
+                    ans.disabled = v
+                        .split(',')
+                        .filter(|v| !v.is_empty())
+                        .map(|v| v.to_string())
+                        .collect()
+                }
+                _ => Err(error(word))?,
+            }
+        }
+
+        Ok(ans)
+    }
+}
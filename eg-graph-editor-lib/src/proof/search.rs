@@ -0,0 +1,192 @@
+use super::{
+    action::Action,
+    error::ProofResult,
+    inference_rule::{InferenceRule, Insertion},
+    rule::Rule,
+    rule_set::RuleSet,
+    Proof,
+};
+use crate::graph::{
+    error::GraphResult, escape_atom, transform_graph_into_canonical_string,
+    transform_graph_into_string, Graph,
+};
+use std::{
+    collections::{BTreeSet, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+use tracing::{debug, instrument};
+
+/// how far a search may go before giving up
+#[derive(Debug, Clone)]
+pub struct SearchLimits {
+    /// the most steps a proof may have
+    pub max_depth: usize,
+
+    /// the most graphs the search may look at
+    pub max_nodes: usize,
+
+    /// how long the search may run for; left unset, the search doesn't look at the clock at all,
+    /// which matters on targets without one
+    pub time_limit: Option<Duration>,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 6,
+            max_nodes: 20_000,
+            time_limit: None,
+        }
+    }
+}
+
+/// a graph reached by the search, and the step that led to it from an earlier one
+struct Node {
+    graph: Graph,
+    depth: usize,
+    from: Option<(usize, InferenceRule)>,
+}
+
+/// searches breadth first for the shortest proof of `goal` from `premise` that `rules` allows
+///
+/// graphs that only differ in their ids are only looked at once; insertions are limited to
+/// pieces of the goal, and double cuts to ones around a single element or a whole area. returns
+/// `None` if no proof was found within the limits
+#[instrument(skip(premise, goal))]
+pub fn search(
+    premise: &Graph,
+    goal: &Graph,
+    rules: &RuleSet,
+    limits: &SearchLimits,
+) -> ProofResult<Option<Proof>> {
+    Ok(search_steps(premise, goal, rules, limits)?.map(|steps| {
+        Proof::new(premise.clone(), steps.into_iter().map(Into::into).collect())
+            .with_rule_set(rules.clone())
+    }))
+}
+
+/// the steps of the proof [`search`] finds, as built in rules
+#[instrument(skip(premise, goal))]
+pub(crate) fn search_steps(
+    premise: &Graph,
+    goal: &Graph,
+    rules: &RuleSet,
+    limits: &SearchLimits,
+) -> ProofResult<Option<Vec<InferenceRule>>> {
+    let goal_string = transform_graph_into_canonical_string(goal, goal.root_id())?;
+    let insertions = insertion_candidates(goal)?;
+    let deadline = limits.time_limit.map(|v| (Instant::now(), v));
+
+    let start = transform_graph_into_canonical_string(premise, premise.root_id())?;
+    if start == goal_string {
+        return Ok(Some(vec![]));
+    }
+
+    let mut visited = HashSet::from([start]);
+    let mut nodes = vec![Node {
+        graph: premise.clone(),
+        depth: 0,
+        from: None,
+    }];
+
+    let mut next = 0;
+    while next < nodes.len() {
+        // nodes are added in order of depth, so nothing after this can be expanded either
+        if nodes[next].depth >= limits.max_depth {
+            break;
+        }
+
+        for step in candidates(&nodes[next].graph, rules, &insertions) {
+            if nodes.len() >= limits.max_nodes
+                || deadline.is_some_and(|(start, limit)| start.elapsed() >= limit)
+            {
+                debug!("Search stopped after {} nodes", nodes.len());
+                return Ok(None);
+            }
+
+            let mut graph = nodes[next].graph.clone();
+            let Ok(actions) = step.gen_actions_with_rules(&graph, rules) else {
+                continue;
+            };
+            if Action::apply_actions(actions, &mut graph).is_err() {
+                continue;
+            }
+
+            let graph_string = transform_graph_into_canonical_string(&graph, graph.root_id())?;
+            if !visited.insert(graph_string.clone()) {
+                continue;
+            }
+
+            nodes.push(Node {
+                graph,
+                depth: nodes[next].depth + 1,
+                from: Some((next, step)),
+            });
+
+            if graph_string == goal_string {
+                return Ok(Some(trace_steps(nodes)));
+            }
+        }
+
+        next += 1;
+    }
+
+    Ok(None)
+}
+
+/// follows the last node back to the premise
+#[instrument(skip(nodes))]
+fn trace_steps(mut nodes: Vec<Node>) -> Vec<InferenceRule> {
+    let mut steps = Vec::new();
+
+    let mut current = nodes.len() - 1;
+    while let Some((parent, step)) = nodes[current].from.take() {
+        steps.push(step);
+        current = parent;
+    }
+    steps.reverse();
+
+    steps
+}
+
+/// the contents the search may insert: every atom and every cut of the goal
+#[instrument(skip(goal))]
+fn insertion_candidates(goal: &Graph) -> GraphResult<Vec<String>> {
+    let mut ans = BTreeSet::new();
+
+    let mut queue = VecDeque::from([*goal.root_id()]);
+    while let Some(id) = queue.pop_front() {
+        for a in goal.atoms_of(&id)? {
+            ans.insert(format!("[{}]", escape_atom(&a.to_string())));
+        }
+
+        for k in goal.subgraphs_of(&id)? {
+            ans.insert(format!("[{}]", transform_graph_into_string(goal, k)?));
+            queue.push_back(*k);
+        }
+    }
+
+    Ok(ans.into_iter().collect())
+}
+
+/// the rule applications worth trying on `graph`, with insertions limited to `insertions`
+///
+/// double cuts are only drawn around single elements and whole areas, since drawing them around
+/// every subset would make a single graph take longer than any limit
+#[instrument(skip(graph, insertions))]
+fn candidates(graph: &Graph, rules: &RuleSet, insertions: &[String]) -> Vec<InferenceRule> {
+    InferenceRule::applicable_bounded(graph, rules)
+        .flat_map(|rule| match rule {
+            InferenceRule::Insertion(Insertion { target, .. }) => insertions
+                .iter()
+                .map(|v| {
+                    InferenceRule::Insertion(Insertion {
+                        target,
+                        new_content: v.clone(),
+                    })
+                })
+                .collect(),
+            rule => vec![rule],
+        })
+        .collect()
+}
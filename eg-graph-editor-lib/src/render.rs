@@ -0,0 +1,5 @@
+pub mod dot;
+pub mod latex;
+pub mod layout;
+pub mod svg;
+pub mod text;
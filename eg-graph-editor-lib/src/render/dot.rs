@@ -0,0 +1,99 @@
+use crate::{
+    graph::{error::GraphResult, Graph, GraphKey},
+    proof::error::Parity,
+};
+use itertools::Itertools;
+use tracing::instrument;
+
+/// fill colour of areas inside an odd number of cuts
+const ODD_FILL: &str = "gray85";
+
+/// fill colour of areas inside an even number of cuts
+const EVEN_FILL: &str = "white";
+
+/// writes the area `id` of `graph` as a Graphviz digraph
+///
+/// every subgraph becomes a cluster labelled with its id, nested the same way the cuts are, and
+/// every atom becomes a node in the cluster of its area. areas inside an odd number of cuts are
+/// shaded
+#[instrument]
+pub fn graph_to_dot(graph: &Graph, id: &GraphKey) -> GraphResult<String> {
+    let mut ans = String::new();
+
+    ans.push_str("digraph {\n");
+    ans.push_str("  compound=true;\n");
+    ans.push_str("  node [shape=plaintext];\n");
+    ans.push_str(&format!("  label={};\n", quote(id.as_ref())));
+    ans.push_str("  style=filled;\n");
+    ans.push_str(&format!("  fillcolor={};\n", fill(graph.level_of(id)?)));
+
+    write_contents(&mut ans, graph, id, 1)?;
+
+    ans.push_str("}\n");
+
+    Ok(ans)
+}
+
+#[instrument(skip(ans, graph))]
+fn write_contents(ans: &mut String, graph: &Graph, id: &GraphKey, depth: usize) -> GraphResult<()> {
+    let indent = "  ".repeat(depth);
+
+    let atoms = graph
+        .atoms_of(id)?
+        .iter()
+        .map(|a| a.to_string())
+        .sorted()
+        .collect_vec();
+    let subgraphs = graph.subgraphs_of(id)?;
+
+    for atom in &atoms {
+        ans.push_str(&format!(
+            "{}{} [label={}];\n",
+            indent,
+            quote(&format!("{}.{}", id, atom)),
+            quote(atom)
+        ));
+    }
+
+    // graphviz leaves out empty clusters
+    if atoms.is_empty() && subgraphs.is_empty() {
+        ans.push_str(&format!(
+            "{}{} [label=\"\", shape=point, style=invis];\n",
+            indent,
+            quote(id.as_ref())
+        ));
+    }
+
+    for k in subgraphs {
+        ans.push_str(&format!(
+            "{}subgraph {} {{\n",
+            indent,
+            quote(&format!("cluster_{}", k))
+        ));
+        ans.push_str(&format!("{}  label={};\n", indent, quote(k.as_ref())));
+        ans.push_str(&format!("{}  style=\"filled,rounded\";\n", indent));
+        ans.push_str(&format!(
+            "{}  fillcolor={};\n",
+            indent,
+            fill(graph.level_of(k)?)
+        ));
+
+        write_contents(ans, graph, k, depth + 1)?;
+
+        ans.push_str(&format!("{}}}\n", indent));
+    }
+
+    Ok(())
+}
+
+fn fill(level: usize) -> &'static str {
+    match Parity::of(level) {
+        Parity::Even => EVEN_FILL,
+        Parity::Odd => ODD_FILL,
+    }
+}
+
+/// writes `s` as a DOT string literal
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
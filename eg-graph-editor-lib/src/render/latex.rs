@@ -0,0 +1,142 @@
+use super::layout::{Layout, Metrics, Placed};
+use crate::{
+    graph::{error::GraphResult, Graph, GraphKey},
+    proof::{action::Action, error::ProofResult, Proof},
+};
+use itertools::Itertools;
+use std::fmt::Write;
+use tracing::instrument;
+
+const METRICS: Metrics = Metrics {
+    char_width: 0.25,
+    atom_height: 0.5,
+    padding: 0.25,
+    gap: 0.3,
+};
+
+/// writes `proof` as a standalone LaTeX document
+///
+/// the premise and the graph after every step are drawn with TikZ, each on a numbered line
+/// together with the rule that justifies it
+#[instrument]
+pub fn proof_to_latex(proof: &Proof) -> ProofResult<String> {
+    let mut ans = String::new();
+
+    ans.push_str("\\documentclass{article}\n");
+    ans.push_str("\\usepackage{tikz}\n");
+    ans.push_str("\\begin{document}\n\n");
+    ans.push_str("\\begin{enumerate}\n");
+    ans.push_str("\\setcounter{enumi}{-1}\n");
+
+    let mut graph = proof.premise().clone();
+    let mut layout = Layout::new(&graph, graph.root_id(), METRICS)?;
+    push_line(&mut ans, &layout, "premise");
+
+    for step in proof.steps() {
+        let actions = step
+            .gen_actions_with_rules(&graph, proof.rule_set())?
+            .into_iter()
+            .collect_vec();
+        Action::apply_actions(actions.clone(), &mut graph)?;
+        layout.relayout(&graph, &actions)?;
+
+        push_line(
+            &mut ans,
+            &layout,
+            &format!(
+                "\\textbf{{{}}}: {}",
+                escape(&step.name().replace('_', " ")),
+                escape(&step.describe())
+            ),
+        );
+    }
+
+    ans.push_str("\\end{enumerate}\n\n");
+    ans.push_str("\\end{document}\n");
+
+    Ok(ans)
+}
+
+/// writes the area `id` of `graph` as a `tikzpicture`, with every cut labelled with its id
+///
+/// lengths are in cm
+#[instrument]
+pub fn graph_to_tikz(graph: &Graph, id: &GraphKey) -> GraphResult<String> {
+    Ok(layout_to_tikz(&Layout::new(graph, id, METRICS)?))
+}
+
+/// writes an existing layout as a `tikzpicture`, using its lengths as cm
+#[instrument(skip(layout))]
+pub fn layout_to_tikz(layout: &Layout) -> String {
+    let height = layout.height();
+    let mut ans = String::new();
+
+    ans.push_str("\\begin{tikzpicture}[baseline=(current bounding box.center)]\n");
+    ans.push_str(&format!(
+        "\\path (0,0) rectangle ({:.2},{:.2});\n",
+        layout.width(),
+        height
+    ));
+
+    // tikz has y growing upwards
+    for v in layout.placed() {
+        match v {
+            Placed::Atom { name, rect, .. } => ans.push_str(&format!(
+                "\\node at ({:.2},{:.2}) {{{}}};\n",
+                rect.x + rect.width / 2.0,
+                height - rect.y - rect.height / 2.0,
+                escape(name)
+            )),
+            Placed::Cut { key, rect } => {
+                ans.push_str(&format!(
+                    "\\draw[rounded corners] ({:.2},{:.2}) rectangle ({:.2},{:.2});\n",
+                    rect.x,
+                    height - rect.y - rect.height,
+                    rect.x + rect.width,
+                    height - rect.y
+                ));
+                ans.push_str(&format!(
+                    "\\node[font=\\tiny, anchor=south west, inner sep=1pt] at ({:.2},{:.2}) {{{}}};\n",
+                    rect.x,
+                    height - rect.y,
+                    escape(key.as_ref())
+                ));
+            }
+        }
+    }
+
+    ans.push_str("\\end{tikzpicture}");
+
+    ans
+}
+
+#[instrument(skip(ans, layout))]
+fn push_line(ans: &mut String, layout: &Layout, justification: &str) {
+    writeln!(
+        ans,
+        "\\item {}\n\n{}\n",
+        layout_to_tikz(layout),
+        justification
+    )
+    .expect("writing to a string can't fail");
+}
+
+/// escapes the characters LaTeX treats specially
+fn escape(s: &str) -> String {
+    let mut ans = String::new();
+
+    for c in s.chars() {
+        match c {
+            '\\' => ans.push_str("\\textbackslash{}"),
+            '~' => ans.push_str("\\textasciitilde{}"),
+            '^' => ans.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                ans.push('\\');
+                ans.push(c);
+            }
+            c => ans.push(c),
+        }
+    }
+
+    ans
+}
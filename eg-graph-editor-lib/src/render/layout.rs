@@ -0,0 +1,299 @@
+use crate::{
+    graph::{error::GraphResult, Graph, GraphKey},
+    proof::action::{Action, GraphTarget},
+};
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+use tracing::instrument;
+
+/// the sizes a layout is built from, in whatever unit the output uses
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    /// width of a single character of an atom
+    pub char_width: f64,
+
+    pub atom_height: f64,
+
+    /// space between a cut and what it contains
+    pub padding: f64,
+
+    /// space between things in the same area
+    pub gap: f64,
+}
+
+/// sizes in px, for a 16px monospace font
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            char_width: 10.0,
+            atom_height: 20.0,
+            padding: 10.0,
+            gap: 12.0,
+        }
+    }
+}
+
+/// a box, with `x` and `y` being its top left corner and `y` growing downwards
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    /// whether `other` lies within this box
+    pub fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+
+    /// whether the two boxes share any area
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+}
+
+/// something to draw, in the order it should be drawn
+#[derive(Debug, Clone, PartialEq)]
+pub enum Placed {
+    Atom {
+        area: GraphKey,
+        name: String,
+        rect: Rect,
+    },
+    Cut {
+        key: GraphKey,
+        rect: Rect,
+    },
+}
+
+/// where every atom and cut of a graph goes
+///
+/// every area is a single row of its atoms, sorted by name, followed by its subgraphs in order,
+/// all centered vertically, so the same graph is always laid out the same way
+#[derive(Debug, Clone)]
+pub struct Layout {
+    metrics: Metrics,
+    root: GraphKey,
+
+    /// the width and height of what each area contains, not counting its padding
+    sizes: HashMap<GraphKey, (f64, f64)>,
+
+    parents: HashMap<GraphKey, GraphKey>,
+    placed: Vec<Placed>,
+    cuts: HashMap<GraphKey, usize>,
+    atoms: HashMap<(GraphKey, String), usize>,
+}
+
+impl Layout {
+    /// lays out everything inside the area `id`
+    #[instrument(skip(graph))]
+    pub fn new(graph: &Graph, id: &GraphKey, metrics: Metrics) -> GraphResult<Self> {
+        let mut ans = Self {
+            metrics,
+            root: *id,
+            sizes: HashMap::new(),
+            parents: HashMap::new(),
+            placed: Vec::new(),
+            cuts: HashMap::new(),
+            atoms: HashMap::new(),
+        };
+
+        ans.measure(graph, id, &HashSet::new())?;
+        ans.place(graph)?;
+
+        Ok(ans)
+    }
+
+    /// updates the layout after `actions` were applied to `graph`
+    ///
+    /// only the areas the actions touch and the areas around them are measured again; everything
+    /// else keeps its size and is only moved
+    #[instrument(skip(self, graph, actions))]
+    pub fn relayout<'a>(
+        &mut self,
+        graph: &Graph,
+        actions: impl IntoIterator<Item = &'a Action>,
+    ) -> GraphResult<()> {
+        let mut touched = Vec::new();
+
+        for action in actions {
+            match action {
+                Action::AddAtom { target, .. }
+                | Action::DeleteAtom { target, .. }
+                | Action::AddSubgraph { target, .. }
+                | Action::DeleteSubgraph { target } => touched.push(target),
+                Action::MoveSubgraph { target, dest } => touched.extend([target, dest]),
+            }
+        }
+
+        let mut dirty = HashSet::new();
+
+        for target in touched {
+            // future targets are new, so they are measured anyway
+            let GraphTarget::Exists(k) = target else {
+                continue;
+            };
+
+            // a deleted or moved subgraph leaves a gap in its old parent
+            let mut next = vec![*k];
+
+            while let Some(k) = next.pop() {
+                if k == self.root || !dirty.insert(k) {
+                    continue;
+                }
+
+                if let Some(parent) = self.parents.get(&k) {
+                    next.push(*parent);
+                }
+                if let Ok(parent) = graph.parent_of(&k) {
+                    next.push(*parent);
+                }
+            }
+        }
+        dirty.insert(self.root);
+
+        self.measure(graph, &self.root.clone(), &dirty)?;
+        self.place(graph)
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// the width of the whole layout
+    pub fn width(&self) -> f64 {
+        self.sizes[&self.root].0
+    }
+
+    /// the height of the whole layout
+    pub fn height(&self) -> f64 {
+        self.sizes[&self.root].1
+    }
+
+    /// everything to draw, with parents before what they contain
+    pub fn placed(&self) -> &[Placed] {
+        &self.placed
+    }
+
+    /// the box drawn for the cut `key`
+    pub fn cut(&self, key: &GraphKey) -> Option<Rect> {
+        self.cuts.get(key).map(|i| self.rect(*i))
+    }
+
+    /// the box the atom `name` takes up in the area `area`
+    pub fn atom(&self, area: &GraphKey, name: &str) -> Option<Rect> {
+        self.atoms
+            .get(&(*area, name.to_string()))
+            .map(|i| self.rect(*i))
+    }
+
+    fn rect(&self, i: usize) -> Rect {
+        match &self.placed[i] {
+            Placed::Atom { rect, .. } | Placed::Cut { rect, .. } => *rect,
+        }
+    }
+
+    /// works out the size of the contents of `id`, reusing the sizes of areas that aren't dirty
+    fn measure(
+        &mut self,
+        graph: &Graph,
+        id: &GraphKey,
+        dirty: &HashSet<GraphKey>,
+    ) -> GraphResult<(f64, f64)> {
+        if let Some(size) = self.sizes.get(id) {
+            if !dirty.contains(id) {
+                return Ok(*size);
+            }
+        }
+
+        let metrics = self.metrics;
+        let mut width = 0.0;
+        let mut height: f64 = 0.0;
+
+        for atom in graph.atoms_of(id)? {
+            width += atom.to_string().chars().count() as f64 * metrics.char_width + metrics.gap;
+            height = height.max(metrics.atom_height);
+        }
+
+        for k in graph.subgraphs_of(id)? {
+            let (w, h) = self.measure(graph, k, dirty)?;
+            width += w + 2.0 * metrics.padding + metrics.gap;
+            height = height.max(h + 2.0 * metrics.padding);
+        }
+
+        let size = (
+            (width - metrics.gap).max(metrics.gap),
+            height.max(metrics.atom_height),
+        );
+        self.sizes.insert(*id, size);
+
+        Ok(size)
+    }
+
+    /// works out where everything goes from the sizes, forgetting areas that are gone
+    fn place(&mut self, graph: &Graph) -> GraphResult<()> {
+        self.placed.clear();
+        self.cuts.clear();
+        self.atoms.clear();
+        self.parents.clear();
+
+        let root = self.root;
+        self.place_contents(graph, &root, 0.0, 0.0)?;
+
+        self.sizes
+            .retain(|k, _| *k == root || self.parents.contains_key(k));
+
+        Ok(())
+    }
+
+    fn place_contents(&mut self, graph: &Graph, id: &GraphKey, x: f64, y: f64) -> GraphResult<()> {
+        let metrics = self.metrics;
+        let middle = y + self.sizes[id].1 / 2.0;
+        let mut x = x;
+
+        for name in graph.atoms_of(id)?.iter().map(|a| a.to_string()).sorted() {
+            let width = name.chars().count() as f64 * metrics.char_width;
+
+            self.atoms.insert((*id, name.clone()), self.placed.len());
+            self.placed.push(Placed::Atom {
+                area: *id,
+                name,
+                rect: Rect {
+                    x,
+                    y: middle - metrics.atom_height / 2.0,
+                    width,
+                    height: metrics.atom_height,
+                },
+            });
+
+            x += width + metrics.gap;
+        }
+
+        for k in graph.subgraphs_of(id)? {
+            let (w, h) = self.sizes[k];
+            let rect = Rect {
+                x,
+                y: middle - h / 2.0 - metrics.padding,
+                width: w + 2.0 * metrics.padding,
+                height: h + 2.0 * metrics.padding,
+            };
+
+            self.parents.insert(*k, *id);
+            self.cuts.insert(*k, self.placed.len());
+            self.placed.push(Placed::Cut { key: *k, rect });
+
+            self.place_contents(graph, k, x + metrics.padding, rect.y + metrics.padding)?;
+            x += rect.width + metrics.gap;
+        }
+
+        Ok(())
+    }
+}
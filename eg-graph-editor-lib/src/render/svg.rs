@@ -0,0 +1,115 @@
+use super::layout::{Layout, Metrics, Placed};
+use crate::graph::{error::GraphResult, Graph, GraphKey};
+use tracing::instrument;
+
+/// space around the whole drawing, in px
+const MARGIN: f64 = 10.0;
+
+/// how cuts are drawn by [`graph_to_svg`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CutShape {
+    RoundedRectangle,
+
+    /// rectangles with fully rounded ends
+    Oval,
+}
+
+#[derive(Debug, Clone)]
+pub struct SvgOptions {
+    pub cut_shape: CutShape,
+
+    /// whether every cut is labelled with its id
+    pub show_ids: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            cut_shape: CutShape::RoundedRectangle,
+            show_ids: false,
+        }
+    }
+}
+
+/// draws the area `id` of `graph` as a standalone SVG document, with every cut as a closed curve
+/// around its contents
+#[instrument]
+pub fn graph_to_svg(graph: &Graph, id: &GraphKey, options: &SvgOptions) -> GraphResult<String> {
+    Ok(layout_to_svg(
+        &Layout::new(graph, id, Metrics::default())?,
+        options,
+    ))
+}
+
+/// draws an existing layout as a standalone SVG document, using its lengths as px
+#[instrument(skip(layout))]
+pub fn layout_to_svg(layout: &Layout, options: &SvgOptions) -> String {
+    let width = layout.width() + 2.0 * MARGIN;
+    let height = layout.height() + 2.0 * MARGIN;
+
+    let mut ans = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0:.1}\" height=\"{1:.1}\" viewBox=\"0 0 {0:.1} {1:.1}\">\n",
+        width, height
+    );
+    ans.push_str(&format!(
+        "<rect width=\"{:.1}\" height=\"{:.1}\" fill=\"white\"/>\n",
+        width, height
+    ));
+
+    for v in layout.placed() {
+        match v {
+            Placed::Atom { name, rect, .. } => ans.push_str(&format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\" font-family=\"monospace\" font-size=\"16\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+                MARGIN + rect.x + rect.width / 2.0,
+                MARGIN + rect.y + rect.height / 2.0,
+                escape(name)
+            )),
+            Placed::Cut { key, rect } => {
+                let radius = match options.cut_shape {
+                    CutShape::RoundedRectangle => layout.metrics().padding,
+                    CutShape::Oval => rect.width.min(rect.height) / 2.0,
+                };
+
+                ans.push_str(&format!(
+                    "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"{:.1}\" fill=\"none\" stroke=\"black\"/>\n",
+                    MARGIN + rect.x,
+                    MARGIN + rect.y,
+                    rect.width,
+                    rect.height,
+                    radius
+                ));
+
+                if options.show_ids {
+                    ans.push_str(&format!(
+                        "<text x=\"{:.1}\" y=\"{:.1}\" font-family=\"monospace\" font-size=\"8\">{}</text>\n",
+                        MARGIN + rect.x + radius / 2.0,
+                        MARGIN + rect.y - 2.0,
+                        escape(key.as_ref())
+                    ));
+                }
+            }
+        }
+    }
+
+    ans.push_str("</svg>\n");
+
+    ans
+}
+
+/// escapes the characters XML treats specially
+fn escape(s: &str) -> String {
+    let mut ans = String::new();
+
+    for c in s.chars() {
+        match c {
+            '&' => ans.push_str("&amp;"),
+            '<' => ans.push_str("&lt;"),
+            '>' => ans.push_str("&gt;"),
+            '"' => ans.push_str("&quot;"),
+            '\'' => ans.push_str("&apos;"),
+            c => ans.push(c),
+        }
+    }
+
+    ans
+}
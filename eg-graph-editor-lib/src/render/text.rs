@@ -0,0 +1,141 @@
+use crate::graph::{error::GraphResult, Graph, GraphKey};
+use itertools::Itertools;
+use tracing::instrument;
+
+#[derive(Debug, Clone)]
+pub struct TextOptions {
+    /// the most columns a line may take up; contents that don't fit wrap onto more rows
+    pub width: usize,
+
+    /// whether every cut has its id written on its top border
+    pub show_ids: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            show_ids: true,
+        }
+    }
+}
+
+/// draws the area `id` of `graph` with box drawing characters, every cut as a box around its
+/// contents
+///
+/// atoms are sorted by name and everything in an area is laid out in rows no wider than
+/// [`TextOptions::width`], as far as the contents allow
+#[instrument]
+pub fn graph_to_text(graph: &Graph, id: &GraphKey, options: &TextOptions) -> GraphResult<String> {
+    let block = area(graph, id, options.width, options)?;
+
+    Ok(block
+        .lines
+        .iter()
+        .map(|v| format!("{}\n", v.trim_end()))
+        .collect())
+}
+
+/// lines of the same width
+struct Block {
+    lines: Vec<String>,
+    width: usize,
+}
+
+impl Block {
+    fn line(s: String) -> Self {
+        Self {
+            width: s.chars().count(),
+            lines: vec![s],
+        }
+    }
+}
+
+fn area(graph: &Graph, id: &GraphKey, width: usize, options: &TextOptions) -> GraphResult<Block> {
+    let mut items = graph
+        .atoms_of(id)?
+        .iter()
+        .map(|a| a.to_string())
+        .sorted()
+        .map(Block::line)
+        .collect_vec();
+
+    for k in graph.subgraphs_of(id)? {
+        items.push(cut(graph, k, width, options)?);
+    }
+
+    // fill rows greedily, starting a new one whenever the next item doesn't fit
+    let mut rows: Vec<Vec<Block>> = Vec::new();
+    let mut row_width = 0;
+
+    for item in items {
+        match rows.last_mut() {
+            Some(row) if row_width + 1 + item.width <= width => {
+                row_width += 1 + item.width;
+                row.push(item);
+            }
+            _ => {
+                row_width = item.width;
+                rows.push(vec![item]);
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+
+    for row in rows {
+        let height = row.iter().map(|v| v.lines.len()).max().unwrap_or(0);
+
+        for i in 0..height {
+            lines.push(
+                row.iter()
+                    .map(|v| {
+                        format!(
+                            "{:width$}",
+                            v.lines.get(i).map(|v| v.as_str()).unwrap_or(""),
+                            width = v.width
+                        )
+                    })
+                    .join(" "),
+            );
+        }
+    }
+
+    let width = lines.iter().map(|v| v.chars().count()).max().unwrap_or(0);
+
+    Ok(Block {
+        lines: lines
+            .into_iter()
+            .map(|v| format!("{:width$}", v, width = width))
+            .collect(),
+        width,
+    })
+}
+
+fn cut(graph: &Graph, id: &GraphKey, width: usize, options: &TextOptions) -> GraphResult<Block> {
+    let contents = area(graph, id, width.saturating_sub(4), options)?;
+    let label = if options.show_ids {
+        id.to_string()
+    } else {
+        String::new()
+    };
+
+    let inner = contents.width.max(label.chars().count());
+
+    let mut lines = vec![format!(
+        "╭─{}{}╮",
+        label,
+        "─".repeat(inner + 1 - label.chars().count())
+    )];
+
+    for line in contents.lines {
+        lines.push(format!("│ {:inner$} │", line, inner = inner));
+    }
+
+    lines.push(format!("╰{}╯", "─".repeat(inner + 2)));
+
+    Ok(Block {
+        lines,
+        width: inner + 4,
+    })
+}
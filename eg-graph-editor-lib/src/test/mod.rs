@@ -1,9 +1,44 @@
 use crate::{
     atom::Atom,
-    graph::Graph,
-    proof::{action::Action, inference_rule::InferenceRule},
+    graph::{
+        error::{GraphError, InvariantViolation},
+        semantics::check,
+        syntax::{read_graph, write_graph, Syntax},
+        transform_graph_into_canonical_string, transform_graph_into_keyed_string,
+        transform_graph_into_string, transform_substituted_graph_into_canonical_string, Graph,
+        GraphKey, Substitution,
+    },
+    proof::{
+        action::{Action, GraphTarget},
+        derived_rule::DerivedRule,
+        error::{Element, Parity, ProofError, ProofResult},
+        hint::hint,
+        inference_rule::{
+            DoubleCutDraw, DoubleCutErase, Erasure, InferenceRule, Insertion, Iteration,
+        },
+        lemma::{Lemma, LemmaLibrary, LemmaRule},
+        minimize::minimize,
+        problem::{parse_problems, write_problems, Goal},
+        recognize::recognize,
+        rule::{Rule, RuleRegistry},
+        rule_set::{DoubleCuts, RuleSet},
+        search::{search, SearchLimits},
+        Proof,
+    },
+    render::{
+        dot::graph_to_dot,
+        latex::proof_to_latex,
+        layout::{Layout, Metrics, Placed},
+        svg::{graph_to_svg, CutShape, SvgOptions},
+        text::{graph_to_text, TextOptions},
+    },
+};
+use std::{
+    collections::{LinkedList, VecDeque},
+    env,
+    str::FromStr,
+    sync::Once,
 };
-use std::{collections::VecDeque, env};
 use std::{error::Error, sync::Arc};
 use tracing::level_filters::LevelFilter;
 use tracing_error::ErrorLayer;
@@ -12,7 +47,15 @@ use tracing_subscriber::{
     fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter,
 };
 
+static INIT_LOGGING: Once = Once::new();
+
 pub fn init_logging() -> Result<(), Box<dyn Error>> {
+    let mut res = Ok(());
+    INIT_LOGGING.call_once(|| res = try_init_logging());
+    res
+}
+
+fn try_init_logging() -> Result<(), Box<dyn Error>> {
     tracing_subscriber::registry()
         .with(
             EnvFilter::builder()
@@ -67,11 +110,11 @@ fn double_cut_draw_test() -> Result<(), Box<dyn Error>> {
         .unwrap()
         .clone();
 
-    let rule = InferenceRule::DoubleCutDraw {
+    let rule = InferenceRule::DoubleCutDraw(DoubleCutDraw {
         target: *graph.root_id(),
         target_atoms: Vec::from([atom_a]),
         target_subgraphs: Vec::new(),
-    };
+    });
 
     let actions = rule.gen_actions_from_rule(&graph)?;
 
@@ -98,9 +141,9 @@ fn double_cut_erase_test() -> Result<(), Box<dyn Error>> {
         ]",
     )?;
 
-    let rule = InferenceRule::DoubleCutErase {
+    let rule = InferenceRule::DoubleCutErase(DoubleCutErase {
         target: *graph.subgraphs_of(graph.root_id())?.iter().next().unwrap(),
-    };
+    });
 
     println!("Original:");
     print_graph(&graph);
@@ -133,21 +176,21 @@ fn iteration_test() -> Result<(), Box<dyn Error>> {
 
     println!("{:?}", subgraphs);
 
-    let rule1 = InferenceRule::Iteration {
+    let rule1 = InferenceRule::Iteration(Iteration {
         backwards: false,
         parent: *graph.root_id(),
         parent_atoms: Vec::from([Arc::new(Atom::new("A".to_string()))]),
         parent_subgraphs: Vec::from([*subgraphs[0]]),
         target: *subgraphs[1],
-    };
+    });
 
-    let rule2 = InferenceRule::Iteration {
+    let rule2 = InferenceRule::Iteration(Iteration {
         backwards: true,
-        parent: *subgraphs[0],
-        parent_atoms: Vec::from([Arc::new(Atom::new("B".to_string()))]),
+        parent: *subgraphs[1],
+        parent_atoms: Vec::from([Arc::new(Atom::new("A".to_string()))]),
         parent_subgraphs: vec![],
         target: *graph.root_id(),
-    };
+    });
 
     println!("Original:");
     print_graph(&graph);
@@ -169,3 +212,1827 @@ fn iteration_test() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn atom_escape_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let mut graph = Graph::try_from("[[]]")?;
+    let root = *graph.root_id();
+    let cut = *graph.subgraphs_of(&root)?.front().unwrap();
+    for name in ["my atom", "a,b", "[c]", "d:e", "50%"] {
+        Action::apply_actions(
+            [Action::AddAtom {
+                target: GraphTarget::Exists(root),
+                atom: Atom::from(name),
+            }],
+            &mut graph,
+        )?;
+    }
+
+    let keyed = transform_graph_into_keyed_string(&graph, &root)?;
+    assert!(keyed.contains("my%20atom"));
+
+    let reread = Graph::try_from(keyed.as_str())?;
+    assert_eq!(
+        transform_graph_into_canonical_string(&reread, reread.root_id())?,
+        transform_graph_into_canonical_string(&graph, &root)?
+    );
+    assert!(reread.atoms_of(&root)?.iter().any(|a| **a == *"my atom"));
+
+    // iterating goes through the graph string, and has to keep the name as it is
+    Action::apply_actions(
+        InferenceRule::Iteration(Iteration {
+            backwards: false,
+            parent: root,
+            parent_atoms: vec![Arc::new(Atom::from("my atom"))],
+            parent_subgraphs: vec![],
+            target: cut,
+        })
+        .gen_actions_from_rule(&graph)?,
+        &mut graph,
+    )?;
+    assert!(graph.atoms_of(&cut)?.iter().any(|a| **a == *"my atom"));
+
+    assert!(Graph::try_from("[a%2]").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn invariants_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let mut graph = Graph::try_from("[A, [B, [C, [A]]], [A, B]]")?;
+    graph.check_invariants()?;
+
+    let nested = *graph.subgraphs_of(graph.root_id())?.iter().next().unwrap();
+
+    let rule = InferenceRule::DoubleCutDraw(DoubleCutDraw {
+        target: *graph.root_id(),
+        target_atoms: vec![],
+        target_subgraphs: vec![nested],
+    });
+
+    let actions_rev = Action::apply_actions(rule.gen_actions_from_rule(&graph)?, &mut graph)?;
+    graph.check_invariants()?;
+    assert_eq!(graph.level_of(&nested)?, 3);
+
+    Action::apply_actions(actions_rev, &mut graph)?;
+    graph.check_invariants()?;
+    assert_eq!(graph.level_of(&nested)?, 1);
+
+    Ok(())
+}
+
+#[test]
+fn invariant_violations_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let violations = |graph: &Graph| match graph.check_invariants() {
+        Err(GraphError::InvariantViolationError(v)) => v,
+        Err(e) => panic!("unexpected error {}", e),
+        Ok(()) => vec![],
+    };
+
+    let graph = Graph::try_from("[A, [B, [C]]]")?;
+    let root = *graph.root_id();
+    let outer = *graph.subgraphs_of(&root)?.front().unwrap();
+    let inner = *graph.subgraphs_of(&outer)?.front().unwrap();
+    let (o, i) = (outer.to_string(), inner.to_string());
+
+    let mut broken = graph.clone();
+    broken.set_level_unchecked(&inner, 5);
+    assert_eq!(
+        violations(&broken),
+        [InvariantViolation::WrongLevel {
+            id: i.clone(),
+            level: 5,
+            depth: 2
+        }]
+    );
+
+    let mut broken = graph.clone();
+    broken.set_atom_count_unchecked(Atom::from("B"), 3);
+    assert_eq!(
+        violations(&broken),
+        [InvariantViolation::WrongAtomCount {
+            atom: "B".to_string(),
+            recorded: 3,
+            actual: 1
+        }]
+    );
+
+    let mut broken = graph.clone();
+    broken.set_parent_unchecked(root, Some(outer));
+    assert!(violations(&broken).contains(&InvariantViolation::RootHasParent { parent: o.clone() }));
+
+    // an orphaned key: nothing points to it any more
+    let mut broken = graph.clone();
+    broken.set_parent_unchecked(inner, None);
+    let found = violations(&broken);
+    assert!(found.contains(&InvariantViolation::MissingParent { id: i.clone() }));
+    assert!(found.contains(&InvariantViolation::ChildHasOtherParent {
+        id: o.clone(),
+        child: i.clone(),
+        parent: None
+    }));
+
+    let mut broken = graph.clone();
+    broken.remove_child_unchecked(&outer, &inner);
+    let found = violations(&broken);
+    assert!(found.contains(&InvariantViolation::NotAChildOfParent {
+        id: i.clone(),
+        parent: o.clone()
+    }));
+    assert!(found.contains(&InvariantViolation::Unreachable { id: i.clone() }));
+
+    let mut broken = graph.clone();
+    broken.set_parent_unchecked(inner, Some(GraphKey::new()));
+    assert!(violations(&broken)
+        .iter()
+        .any(|v| matches!(v, InvariantViolation::ParentDoesNotExist { id, .. } if *id == i)));
+
+    let mut broken = graph.clone();
+    let missing = GraphKey::new();
+    broken.set_parent_unchecked(missing, Some(root));
+    assert_eq!(
+        violations(&broken),
+        [InvariantViolation::DanglingParentEntry {
+            id: missing.to_string()
+        }]
+    );
+
+    // the original was never touched
+    assert!(violations(&graph).is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn errors_instead_of_panics_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let mut graph = Graph::try_from("[A, [B, [C]], [A]]")?;
+    let root = *graph.root_id();
+    let subgraphs: Vec<_> = graph.subgraphs_of(&root)?.iter().cloned().collect();
+    let inner = *graph.subgraphs_of(&subgraphs[0])?.iter().next().unwrap();
+
+    // removing atoms that aren't there
+    assert!(graph
+        .remove_atom_from_subgraph(&root, &Atom::from("Z"))
+        .is_err());
+    assert!(graph
+        .remove_atom_from_subgraph(&root, &Atom::from("B"))
+        .is_err());
+    graph.check_invariants()?;
+
+    // moving a cut into itself or one of its own subgraphs
+    assert!(graph.move_subgraph(&subgraphs[0], &subgraphs[0]).is_err());
+    assert!(graph.move_subgraph(&subgraphs[0], &inner).is_err());
+    graph.check_invariants()?;
+
+    // a failing batch of actions leaves the graph as it was
+    let before = String::try_from(&graph)?;
+    assert!(Action::apply_actions(
+        [
+            Action::AddAtom {
+                target: GraphTarget::Exists(root),
+                atom: "D".into(),
+            },
+            Action::DeleteSubgraph {
+                target: GraphTarget::Exists(subgraphs[0]),
+            },
+        ],
+        &mut graph,
+    )
+    .is_err());
+    assert_eq!(String::try_from(&graph)?, before);
+    graph.check_invariants()?;
+
+    // atoms handed out by the graph can outlive their removal
+    let atom_c = graph.atoms_of(&inner)?.iter().next().unwrap().clone();
+    graph.remove_atom_from_subgraph(&inner, &atom_c)?;
+    assert!(!graph.atoms().any(|a| a == &atom_c));
+
+    graph.remove_subgraph(&subgraphs[0], true)?;
+    assert!(graph.level_of(&inner).is_err());
+    graph.check_invariants()?;
+
+    assert!(transform_graph_into_string(&graph, &inner).is_err());
+    assert!(graph.remove_subgraph(&root, true).is_err());
+
+    // the sheet can't be erased like a cut can
+    assert!(matches!(
+        InferenceRule::Erasure(Erasure {
+            target_subgraphs: vec![root],
+            target_atoms: vec![],
+        })
+        .gen_actions_from_rule(&graph),
+        Err(ProofError::SheetTargeted { target }) if target == root
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn rule_errors_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let graph = Graph::try_from("[A, [B, [C]], [[D], E]]")?;
+    let root = *graph.root_id();
+    let subgraphs: Vec<_> = graph.subgraphs_of(&root)?.iter().cloned().collect();
+    let inner = *graph.subgraphs_of(&subgraphs[0])?.iter().next().unwrap();
+
+    let err = InferenceRule::Erasure(Erasure {
+        target_subgraphs: vec![inner],
+        target_atoms: vec![],
+    })
+    .gen_actions_from_rule(&graph)
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ProofError::WrongParity {
+            target,
+            expected: Parity::Even,
+            level: 1,
+        } if target == inner
+    ));
+    assert_eq!(err.offending_keys(), vec![inner]);
+
+    let err = InferenceRule::Insertion(Insertion {
+        target: root,
+        new_content: "[F]".to_string(),
+    })
+    .gen_actions_from_rule(&graph)
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ProofError::WrongParity {
+            expected: Parity::Odd,
+            level: 0,
+            ..
+        }
+    ));
+
+    let err = InferenceRule::DoubleCutDraw(DoubleCutDraw {
+        target: root,
+        target_atoms: vec![Arc::new(Atom::from("B"))],
+        target_subgraphs: vec![],
+    })
+    .gen_actions_from_rule(&graph)
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ProofError::ElementNotInArea {
+            area,
+            element: Element::Atom(ref a),
+        } if area == root && **a == *"B"
+    ));
+
+    let err = InferenceRule::DoubleCutErase(DoubleCutErase {
+        target: subgraphs[1],
+    })
+    .gen_actions_from_rule(&graph)
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ProofError::OuterCutNotEmpty { ref atoms, .. } if atoms.len() == 1
+    ));
+
+    let err = InferenceRule::Iteration(Iteration {
+        backwards: false,
+        parent: subgraphs[0],
+        parent_atoms: vec![Arc::new(Atom::from("B"))],
+        parent_subgraphs: vec![],
+        target: subgraphs[1],
+    })
+    .gen_actions_from_rule(&graph)
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ProofError::NotEnclosed { outer, target } if outer == subgraphs[0] && target == subgraphs[1]
+    ));
+
+    let err = InferenceRule::Iteration(Iteration {
+        backwards: false,
+        parent: root,
+        parent_atoms: vec![],
+        parent_subgraphs: vec![subgraphs[0]],
+        target: inner,
+    })
+    .gen_actions_from_rule(&graph)
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ProofError::TargetInsideSelection { target, subgraph } if target == inner && subgraph == subgraphs[0]
+    ));
+
+    let err = InferenceRule::Iteration(Iteration {
+        backwards: true,
+        parent: inner,
+        parent_atoms: vec![Arc::new(Atom::from("C"))],
+        parent_subgraphs: vec![],
+        target: root,
+    })
+    .gen_actions_from_rule(&graph)
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ProofError::ElementNotInContext { context, .. } if context == root
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn rule_registry_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let graph = Graph::try_from("[A, [B, [C]], [[D]]]")?;
+    let root = *graph.root_id();
+    let subgraphs: Vec<_> = graph.subgraphs_of(&root)?.iter().cloned().collect();
+
+    let rules = [
+        InferenceRule::DoubleCutDraw(DoubleCutDraw {
+            target: root,
+            target_atoms: vec![Arc::new(Atom::from("A"))],
+            target_subgraphs: vec![subgraphs[0]],
+        }),
+        InferenceRule::DoubleCutErase(DoubleCutErase {
+            target: subgraphs[1],
+        }),
+        InferenceRule::Insertion(Insertion {
+            target: subgraphs[0],
+            new_content: "[E, [F]]".to_string(),
+        }),
+        InferenceRule::Erasure(Erasure {
+            target_subgraphs: vec![subgraphs[0]],
+            target_atoms: vec![(root, Arc::new(Atom::from("A")))],
+        }),
+        InferenceRule::Iteration(Iteration {
+            backwards: true,
+            parent: subgraphs[0],
+            parent_atoms: vec![],
+            parent_subgraphs: vec![],
+            target: root,
+        }),
+    ];
+
+    let registry = RuleRegistry::new();
+
+    for rule in rules {
+        let parsed = registry.parse(&rule.serialize())?;
+
+        assert_eq!(parsed.serialize(), rule.serialize());
+        assert_eq!(parsed.name(), rule.name());
+        parsed.validate(&graph)?;
+    }
+
+    // contents and atom names keep their spaces and separators
+    let rule = InferenceRule::Insertion(Insertion {
+        target: subgraphs[0],
+        new_content: "[long name, [B%]]".to_string(),
+    });
+    assert!(!rule.to_string().contains("long name"));
+    assert!(matches!(
+        InferenceRule::from_str(&rule.to_string())?,
+        InferenceRule::Insertion(Insertion { new_content, .. }) if new_content == "[long name, [B%]]"
+    ));
+
+    let rule = InferenceRule::Erasure(Erasure {
+        target_subgraphs: vec![],
+        target_atoms: vec![(root, Arc::new(Atom::from("odd, name:x")))],
+    });
+    assert!(matches!(
+        InferenceRule::from_str(&rule.to_string())?,
+        InferenceRule::Erasure(Erasure { target_atoms, .. }) if target_atoms[0].1.to_string() == "odd, name:x"
+    ));
+    assert!(InferenceRule::from_str(&format!("insertion target={} content=%4", root)).is_err());
+
+    // custom rules only need to implement the trait and be registered
+    #[derive(Debug)]
+    struct EraseEverything;
+
+    impl Rule for EraseEverything {
+        fn name(&self) -> &str {
+            "erase_everything"
+        }
+
+        fn describe(&self) -> String {
+            "Erase everything on the sheet of assertion".to_string()
+        }
+
+        fn gen_actions(&self, graph: &Graph) -> ProofResult<LinkedList<Action>> {
+            let root = *graph.root_id();
+
+            InferenceRule::Erasure(Erasure {
+                target_subgraphs: graph.subgraphs_of(&root)?.iter().cloned().collect(),
+                target_atoms: graph
+                    .atoms_of(&root)?
+                    .iter()
+                    .map(|a| (root, a.clone()))
+                    .collect(),
+            })
+            .gen_actions(graph)
+        }
+
+        fn serialize(&self) -> String {
+            self.name().to_string()
+        }
+    }
+
+    let mut registry = RuleRegistry::new();
+    registry.register("erase_everything", |_| Ok(Box::new(EraseEverything)));
+
+    let mut graph = graph;
+    let rule = registry.parse("erase_everything")?;
+    Action::apply_actions(rule.gen_actions(&graph)?, &mut graph)?;
+    assert_eq!(String::try_from(&graph)?, "[]");
+
+    // and can then be used as proof steps, next to the built in ones
+    let proof = Proof::new(
+        graph.clone(),
+        vec![
+            Arc::new(DoubleCutDraw {
+                target: *graph.root_id(),
+                target_atoms: vec![],
+                target_subgraphs: vec![],
+            }),
+            Arc::new(EraseEverything),
+        ],
+    );
+    assert_eq!(String::try_from(&proof.conclusion()?)?, "[]");
+
+    let reread = Proof::parse(&proof.to_string(), &registry)?;
+    assert_eq!(reread.to_string(), proof.to_string());
+    assert_eq!(String::try_from(&reread.conclusion()?)?, "[]");
+    assert!(matches!(
+        Proof::from_str(&proof.to_string()),
+        Err(ProofError::UnknownRule(_))
+    ));
+
+    assert!(matches!(
+        registry.parse("not_a_rule target=abc"),
+        Err(ProofError::UnknownRule(_))
+    ));
+    assert!(matches!(
+        registry.parse("double_cut_erase"),
+        Err(ProofError::RuleParseError(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn derived_rules_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    fn first_cut(graph: &Graph, n: usize) -> GraphKey {
+        *graph
+            .subgraphs_of(graph.root_id())
+            .unwrap()
+            .iter()
+            .nth(n)
+            .unwrap()
+    }
+
+    type Case = (&'static str, &'static str, fn(&Graph) -> DerivedRule);
+
+    let cases: [Case; 6] = [
+        ("[A, [A, [B]]]", "[A,B]", |g| DerivedRule::ModusPonens {
+            premise: Element::Atom(Arc::new(Atom::from("A"))),
+            implication: first_cut(g, 0),
+        }),
+        ("[[C], [[C], [B, D]]]", "[B,D,[C]]", |g| {
+            DerivedRule::ModusPonens {
+                premise: Element::Subgraph(first_cut(g, 0)),
+                implication: first_cut(g, 1),
+            }
+        }),
+        ("[[Q], [P, [Q]]]", "[[P],[Q]]", |g| {
+            DerivedRule::ModusTollens {
+                negation: first_cut(g, 0),
+                implication: first_cut(g, 1),
+            }
+        }),
+        ("[[P], [[P], [Q]]]", "[Q,[P]]", |g| {
+            DerivedRule::DisjunctiveSyllogism {
+                negation: first_cut(g, 0),
+                disjunction: first_cut(g, 1),
+            }
+        }),
+        ("[[P, [Q]]]", "[[[Q],[[P]]]]", |g| {
+            DerivedRule::Contraposition {
+                implication: first_cut(g, 0),
+            }
+        }),
+        ("[[P, [Q]], [Q, [R]]]", "[[P,[R]],[Q,[R]]]", |g| {
+            DerivedRule::HypotheticalSyllogism {
+                first: first_cut(g, 0),
+                second: first_cut(g, 1),
+            }
+        }),
+    ];
+
+    let registry = RuleRegistry::new();
+
+    for (before, after, rule) in cases {
+        let graph = Graph::try_from(before)?;
+        let rule = rule(&graph);
+
+        // applying the rule directly
+        let mut direct = graph.clone();
+        Action::apply_actions(rule.gen_actions(&direct)?, &mut direct)?;
+        assert_eq!(
+            transform_graph_into_canonical_string(&direct, direct.root_id())?,
+            after
+        );
+
+        // replaying the expansion one primitive step at a time
+        let mut replayed = graph.clone();
+        for step in rule.expand(&graph)? {
+            Action::apply_actions(step.gen_actions_from_rule(&replayed)?, &mut replayed)?;
+        }
+        assert_eq!(String::try_from(&replayed)?, String::try_from(&direct)?);
+
+        assert_eq!(
+            registry.parse(&rule.serialize())?.serialize(),
+            rule.serialize()
+        );
+    }
+
+    let graph = Graph::try_from("[A, [B, [C]]]")?;
+    assert!(matches!(
+        DerivedRule::ModusPonens {
+            premise: Element::Atom(Arc::new(Atom::from("A"))),
+            implication: first_cut(&graph, 0),
+        }
+        .expand(&graph),
+        Err(ProofError::ElementNotInArea { .. })
+    ));
+
+    // each step of the expansion has to be allowed
+    let graph = Graph::try_from("[A, [A, [B]]]")?;
+    let modus_ponens = DerivedRule::ModusPonens {
+        premise: Element::Atom(Arc::new(Atom::from("A"))),
+        implication: first_cut(&graph, 0),
+    };
+    assert!(modus_ponens
+        .gen_actions_with_rules(&graph, &RuleSet::nested_iteration())
+        .is_ok());
+    assert!(modus_ponens
+        .gen_actions_with_rules(&graph, &RuleSet::peirce().without("insertion"))
+        .is_ok());
+    assert!(matches!(
+        modus_ponens.gen_actions_with_rules(&graph, &RuleSet::peirce().without("deiteration")),
+        Err(ProofError::RuleDisabled(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn lemma_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let key = |v: &str| GraphKey::try_from_str(v).unwrap();
+
+    // modus ponens, proven once from `A` and `A implies B`
+    let premise = Graph::try_from("rrr:[A, iii:[A, ccc:[B]]]")?;
+    assert_eq!(
+        transform_graph_into_keyed_string(&premise, premise.root_id())?,
+        "rrr:[A,iii:[A,ccc:[B]]]"
+    );
+
+    let lemma = Lemma::new(
+        "mp",
+        premise,
+        vec![
+            InferenceRule::Iteration(Iteration {
+                backwards: true,
+                parent: key("iii"),
+                parent_atoms: vec![Arc::new(Atom::from("A"))],
+                parent_subgraphs: vec![],
+                target: key("rrr"),
+            }),
+            InferenceRule::DoubleCutErase(DoubleCutErase { target: key("iii") }),
+        ],
+    )?;
+    assert_eq!(
+        transform_graph_into_canonical_string(lemma.conclusion(), lemma.conclusion().root_id())?,
+        "[A,B]"
+    );
+
+    let mut library = LemmaLibrary::new();
+    library.insert(lemma);
+    let library = LemmaLibrary::from_str(&library.to_string())?;
+
+    let mut registry = RuleRegistry::new();
+    library.register_all(&mut registry);
+
+    let substitution = |pairs: &[(&str, &str)]| -> Result<Substitution, Box<dyn Error>> {
+        pairs
+            .iter()
+            .map(|(a, g)| Ok((Atom::from(*a), Graph::try_from(*g)?)))
+            .collect()
+    };
+
+    // renaming atoms, with other things in the area left alone
+    let graph = Graph::try_from("[P, [P, [Q]], [X]]")?;
+    let rule = LemmaRule {
+        lemma: library.get("mp").unwrap().clone(),
+        area: *graph.root_id(),
+        atoms: vec![Arc::new(Atom::from("P"))],
+        subgraphs: vec![*graph.subgraphs_of(graph.root_id())?.front().unwrap()],
+        substitution: substitution(&[("A", "[P]"), ("B", "[Q]")])?,
+    };
+
+    let mut direct = graph.clone();
+    Action::apply_actions(rule.gen_actions(&direct)?, &mut direct)?;
+    assert_eq!(
+        transform_graph_into_canonical_string(&direct, direct.root_id())?,
+        "[P,Q,[X]]"
+    );
+
+    // the lemma can't be used where its proof couldn't be given
+    assert!(rule
+        .gen_actions_with_rules(&graph, &RuleSet::nested_iteration())
+        .is_ok());
+    assert!(matches!(
+        rule.gen_actions_with_rules(&graph, &RuleSet::peirce().without("double_cut_erase")),
+        Err(ProofError::RuleDisabled(_))
+    ));
+
+    let mut replayed = graph.clone();
+    for step in rule.expand(&graph)? {
+        Action::apply_actions(step.gen_actions(&replayed)?, &mut replayed)?;
+    }
+    assert_eq!(
+        transform_graph_into_canonical_string(&replayed, replayed.root_id())?,
+        transform_graph_into_canonical_string(&direct, direct.root_id())?
+    );
+
+    assert_eq!(
+        registry.parse(&rule.serialize())?.serialize(),
+        rule.serialize()
+    );
+
+    // substituting a whole graph can be applied, but not expanded
+    let graph = Graph::try_from("[[R], [[R], [Q]]]")?;
+    let subgraphs = graph
+        .subgraphs_of(graph.root_id())?
+        .iter()
+        .cloned()
+        .collect::<Vec<_>>();
+    let rule = LemmaRule {
+        lemma: library.get("mp").unwrap().clone(),
+        area: *graph.root_id(),
+        atoms: vec![],
+        subgraphs: subgraphs.clone(),
+        substitution: substitution(&[("A", "[[R]]"), ("B", "[Q]")])?,
+    };
+
+    let mut direct = graph.clone();
+    Action::apply_actions(rule.gen_actions(&direct)?, &mut direct)?;
+    assert_eq!(
+        transform_graph_into_canonical_string(&direct, direct.root_id())?,
+        "[Q,[R]]"
+    );
+    assert!(matches!(
+        rule.expand(&graph),
+        Err(ProofError::NotExpandable(_))
+    ));
+
+    // only instances of the premise can be replaced
+    let wrong = LemmaRule {
+        subgraphs: subgraphs[1..].to_vec(),
+        ..rule.clone()
+    };
+    assert!(matches!(
+        wrong.gen_actions(&graph),
+        Err(ProofError::NotAnInstance { .. })
+    ));
+
+    // and only in positive areas
+    let wrong = LemmaRule {
+        area: subgraphs[1],
+        subgraphs: vec![],
+        ..rule
+    };
+    assert!(matches!(
+        wrong.gen_actions(&graph),
+        Err(ProofError::WrongParity { .. })
+    ));
+
+    assert!(matches!(
+        registry.parse("lemma name=nope area=aaa atoms= subgraphs="),
+        Err(ProofError::UnknownLemma(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn substitution_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let mut graph = Graph::try_from("[A, B, [A, [B, C]]]")?;
+    let before = transform_graph_into_canonical_string(&graph, graph.root_id())?;
+
+    // substitutes are put in simultaneously, so the `A` that replaces `B` stays
+    let substitution: Substitution = [("A", "[[D]]"), ("B", "[A]")]
+        .into_iter()
+        .map(|(a, g)| Ok((Atom::from(a), Graph::try_from(g)?)))
+        .collect::<Result<_, Box<dyn Error>>>()?;
+
+    let expected = transform_substituted_graph_into_canonical_string(
+        &graph,
+        &graph.root_id().clone(),
+        &substitution,
+    )?;
+    assert_eq!(expected, "[A,[D],[[A,C],[D]]]");
+
+    let undo = Action::apply_actions(graph.substitute(&substitution)?, &mut graph)?;
+    assert_eq!(
+        transform_graph_into_canonical_string(&graph, graph.root_id())?,
+        expected
+    );
+    graph.check_invariants()?;
+
+    Action::apply_actions(undo, &mut graph)?;
+    assert_eq!(
+        transform_graph_into_canonical_string(&graph, graph.root_id())?,
+        before
+    );
+
+    // renaming into an atom the area already has merges the two
+    let mut graph = Graph::try_from("[A, C, [C]]")?;
+    let undo = Action::apply_actions(
+        graph.rename_atom(&Atom::from("C"), Atom::from("A"))?,
+        &mut graph,
+    )?;
+    assert_eq!(String::try_from(&graph)?, "[A,[A]]");
+    assert_eq!(graph.atoms().count(), 1);
+
+    Action::apply_actions(undo, &mut graph)?;
+    assert_eq!(
+        transform_graph_into_canonical_string(&graph, graph.root_id())?,
+        "[A,C,[C]]"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn search_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let limits = SearchLimits::default();
+
+    for (premise, goal, length) in [
+        ("[A, [A, [B]]]", "[A, B]", 2),
+        ("[A, B]", "[B]", 1),
+        ("[[[A]]]", "[A]", 1),
+        ("[A]", "[A, [B, [A]]]", 3),
+    ] {
+        let premise = Graph::try_from(premise)?;
+        let goal = Graph::try_from(goal)?;
+
+        let proof =
+            search(&premise, &goal, &RuleSet::default(), &limits)?.expect("proof should be found");
+        assert_eq!(proof.steps().len(), length);
+
+        let conclusion = proof.conclusion()?;
+        assert_eq!(
+            transform_graph_into_canonical_string(&conclusion, conclusion.root_id())?,
+            transform_graph_into_canonical_string(&goal, goal.root_id())?
+        );
+    }
+
+    // wide areas don't keep the search from getting anywhere
+    let atoms = (0..40).map(|i| format!("A{}", i)).collect::<Vec<_>>();
+    let premise = Graph::try_from(format!("[{}]", atoms.join(",")).as_str())?;
+    let goal = Graph::try_from(format!("[{}]", atoms[1..].join(",")).as_str())?;
+    let proof =
+        search(&premise, &goal, &RuleSet::default(), &limits)?.expect("proof should be found");
+    assert_eq!(proof.steps().len(), 1);
+
+    // nothing can be proven from nothing
+    assert!(search(
+        &Graph::try_from("[]")?,
+        &Graph::try_from("[A]")?,
+        &RuleSet::default(),
+        &SearchLimits {
+            max_depth: 3,
+            ..Default::default()
+        }
+    )?
+    .is_none());
+
+    Ok(())
+}
+
+#[test]
+fn applicable_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let graph = Graph::try_from("[A, [A, B], [[C]]]")?;
+    let root = *graph.root_id();
+    let cuts = graph
+        .subgraphs_of(&root)?
+        .iter()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let rules = InferenceRule::applicable(&graph, &RuleSet::default()).collect::<Vec<_>>();
+
+    for rule in &rules {
+        match rule {
+            InferenceRule::Insertion(Insertion {
+                target,
+                new_content,
+            }) => {
+                assert!(new_content.is_empty());
+                assert_eq!(graph.level_of(target)? % 2, 1);
+            }
+            rule => assert!(rule.gen_actions_from_rule(&graph).is_ok(), "{}", rule),
+        }
+    }
+
+    let count = |f: fn(&InferenceRule) -> bool| rules.iter().filter(|v| f(v)).count();
+
+    // every subset of the root, and of each cut
+    assert_eq!(
+        count(|v| matches!(v, InferenceRule::DoubleCutDraw(DoubleCutDraw { .. }))),
+        8 + 4 + 2 + 2
+    );
+    assert_eq!(
+        count(|v| matches!(v, InferenceRule::Insertion(Insertion { .. }))),
+        2
+    );
+    assert_eq!(
+        count(|v| matches!(v, InferenceRule::DoubleCutErase(DoubleCutErase { .. }))),
+        1
+    );
+
+    assert!(rules.contains(&InferenceRule::Iteration(Iteration {
+        backwards: true,
+        parent: cuts[0],
+        parent_atoms: vec![Arc::new(Atom::from("A"))],
+        parent_subgraphs: vec![],
+        target: root,
+    })));
+    assert!(rules.contains(&InferenceRule::Erasure(Erasure {
+        target_subgraphs: vec![cuts[1]],
+        target_atoms: vec![],
+    })));
+    assert!(!rules.iter().any(|v| matches!(
+        v,
+        InferenceRule::Erasure(Erasure { target_atoms, .. }) if target_atoms.iter().any(|(k, _)| *k == cuts[0])
+    )));
+
+    // several elements at once
+    let together = InferenceRule::applicable_to(
+        &graph,
+        &RuleSet::default(),
+        root,
+        vec![Arc::new(Atom::from("A"))],
+        vec![cuts[1]],
+    )
+    .collect::<Vec<_>>();
+    assert!(together.contains(&InferenceRule::Erasure(Erasure {
+        target_subgraphs: vec![cuts[1]],
+        target_atoms: vec![(root, Arc::new(Atom::from("A")))],
+    })));
+    assert!(
+        together.contains(&InferenceRule::DoubleCutDraw(DoubleCutDraw {
+            target: root,
+            target_atoms: vec![Arc::new(Atom::from("A"))],
+            target_subgraphs: vec![cuts[1]],
+        }))
+    );
+    assert!(!together
+        .iter()
+        .any(|v| matches!(v, InferenceRule::DoubleCutErase(_))));
+
+    // the bounded list only draws around single elements and whole areas
+    let draws = InferenceRule::applicable_bounded(&graph, &RuleSet::default())
+        .filter(|v| matches!(v, InferenceRule::DoubleCutDraw(DoubleCutDraw { .. })))
+        .count();
+    assert_eq!(draws, (1 + 3 + 1) + (1 + 2 + 1) + (1 + 1) + (1 + 1));
+
+    // areas too wide to list every subset of are still only worked out as far as they're taken
+    let atoms = (0..80).map(|i| format!("A{}", i)).collect::<Vec<_>>();
+    let wide = Graph::try_from(format!("[{}]", atoms.join(",")).as_str())?;
+    assert_eq!(
+        InferenceRule::applicable(&wide, &RuleSet::default())
+            .take(1000)
+            .count(),
+        1000
+    );
+
+    Ok(())
+}
+
+#[test]
+fn hint_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let limits = SearchLimits::default();
+
+    let graph = Graph::try_from("[A, [[B]]]")?;
+    let next = hint(
+        &graph,
+        &Graph::try_from("[A, B]")?,
+        &RuleSet::default(),
+        &limits,
+    )?
+    .expect("hint should be found");
+    assert!(matches!(
+        next.rule,
+        InferenceRule::DoubleCutErase(DoubleCutErase { .. })
+    ));
+    assert_eq!(next.explanation, "Removes a double cut around the goal's B");
+    assert_eq!(next.remaining, 0);
+
+    let graph = Graph::try_from("[A, C, [A, [B]]]")?;
+    let next = hint(
+        &graph,
+        &Graph::try_from("[A, B]")?,
+        &RuleSet::default(),
+        &limits,
+    )?
+    .expect("hint should be found");
+    assert_eq!(next.remaining, 2);
+
+    // nothing to hint at once the goal is reached
+    assert!(hint(&graph, &graph, &RuleSet::default(), &limits)?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn minimize_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    // builds up a proof, handing each step the graph as it is at that point
+    fn build(
+        premise: &str,
+        steps: &[fn(&Graph) -> InferenceRule],
+    ) -> Result<Proof, Box<dyn Error>> {
+        let premise = Graph::try_from(premise)?;
+        let mut graph = premise.clone();
+        let mut ans = Vec::new();
+
+        for step in steps {
+            let step = step(&graph);
+            Action::apply_actions(step.gen_actions_from_rule(&graph)?, &mut graph)?;
+            ans.push(step.into());
+        }
+
+        Ok(Proof::new(premise, ans))
+    }
+
+    fn newest(graph: &Graph, n: usize) -> GraphKey {
+        *graph.subgraph_keys().rev().nth(n).unwrap()
+    }
+
+    // a double cut drawn and erased straight away
+    let proof = build(
+        "[A, B]",
+        &[
+            |g| {
+                InferenceRule::DoubleCutDraw(DoubleCutDraw {
+                    target: *g.root_id(),
+                    target_atoms: vec![Arc::new(Atom::from("B"))],
+                    target_subgraphs: vec![],
+                })
+            },
+            |g| {
+                InferenceRule::DoubleCutErase(DoubleCutErase {
+                    target: newest(g, 1),
+                })
+            },
+            |g| {
+                InferenceRule::Erasure(Erasure {
+                    target_subgraphs: vec![],
+                    target_atoms: vec![(*g.root_id(), Arc::new(Atom::from("B")))],
+                })
+            },
+        ],
+    )?;
+
+    let minimized = minimize(&proof)?;
+    assert_eq!(minimized.removed, vec![0, 1]);
+    assert_eq!(
+        minimized.proof.steps()[0].serialize(),
+        proof.steps()[2].serialize()
+    );
+    assert_eq!(minimized.proof.steps().len(), 1);
+
+    // an unneeded double cut drawn first changes the ids of the one drawn after it
+    let proof = build(
+        "[A]",
+        &[
+            |g| {
+                InferenceRule::DoubleCutDraw(DoubleCutDraw {
+                    target: *g.root_id(),
+                    target_atoms: vec![],
+                    target_subgraphs: vec![],
+                })
+            },
+            |g| {
+                InferenceRule::DoubleCutDraw(DoubleCutDraw {
+                    target: *g.root_id(),
+                    target_atoms: vec![],
+                    target_subgraphs: vec![],
+                })
+            },
+            |g| {
+                InferenceRule::Iteration(Iteration {
+                    backwards: false,
+                    parent: *g.root_id(),
+                    parent_atoms: vec![Arc::new(Atom::from("A"))],
+                    parent_subgraphs: vec![],
+                    target: newest(g, 0),
+                })
+            },
+            |g| {
+                InferenceRule::Erasure(Erasure {
+                    target_subgraphs: vec![newest(g, 3)],
+                    target_atoms: vec![],
+                })
+            },
+        ],
+    )?;
+
+    let minimized = minimize(&proof)?;
+    assert_eq!(minimized.removed, vec![0, 3]);
+    assert_eq!(minimized.proof.steps().len(), 2);
+
+    let before = proof.conclusion()?;
+    let after = minimized.proof.conclusion()?;
+    assert_eq!(
+        transform_graph_into_canonical_string(&after, after.root_id())?,
+        transform_graph_into_canonical_string(&before, before.root_id())?
+    );
+
+    // the steps are replayed, and the result kept, under the proof's own rules
+    let proof = build(
+        "[A, B]",
+        &[
+            |g| {
+                InferenceRule::DoubleCutDraw(DoubleCutDraw {
+                    target: *g.root_id(),
+                    target_atoms: vec![],
+                    target_subgraphs: vec![],
+                })
+            },
+            |g| {
+                InferenceRule::DoubleCutErase(DoubleCutErase {
+                    target: newest(g, 1),
+                })
+            },
+            |g| {
+                InferenceRule::Erasure(Erasure {
+                    target_subgraphs: vec![],
+                    target_atoms: vec![(*g.root_id(), Arc::new(Atom::from("B")))],
+                })
+            },
+        ],
+    )?
+    .with_rule_set(RuleSet::empty_double_cuts());
+
+    let minimized = minimize(&proof)?;
+    assert_eq!(minimized.removed, vec![0, 1]);
+    assert_eq!(minimized.proof.rule_set(), &RuleSet::empty_double_cuts());
+
+    let proof = build(
+        "[A, B]",
+        &[|g| {
+            InferenceRule::DoubleCutDraw(DoubleCutDraw {
+                target: *g.root_id(),
+                target_atoms: vec![Arc::new(Atom::from("B"))],
+                target_subgraphs: vec![],
+            })
+        }],
+    )?
+    .with_rule_set(RuleSet::empty_double_cuts());
+    assert!(minimize(&proof).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn recognize_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let before = Graph::try_from("[A, [B]]")?;
+    let recognize = |after: &str| -> Result<Option<InferenceRule>, Box<dyn Error>> {
+        Ok(recognize(
+            &before,
+            &Graph::try_from(after)?,
+            &RuleSet::default(),
+        )?)
+    };
+
+    assert!(matches!(
+        recognize("[A, [C, B]]")?,
+        Some(InferenceRule::Insertion(Insertion { new_content, .. })) if new_content == "[C]"
+    ));
+    assert!(matches!(
+        recognize("[[B]]")?,
+        Some(InferenceRule::Erasure(Erasure { .. }))
+    ));
+    assert!(matches!(
+        recognize("[A, [[[B]]]]")?,
+        Some(InferenceRule::DoubleCutDraw(DoubleCutDraw { .. }))
+    ));
+    assert!(matches!(
+        recognize("[A, [A, B]]")?,
+        Some(InferenceRule::Iteration(Iteration {
+            backwards: false,
+            ..
+        }))
+    ));
+    assert!(recognize("[B]")?.is_none());
+
+    // several elements at once
+    let many = Graph::try_from("[A, B, C, [A, B, D]]")?;
+    let recognize = |after: &str| -> Result<Option<InferenceRule>, Box<dyn Error>> {
+        Ok(crate::proof::recognize::recognize(
+            &many,
+            &Graph::try_from(after)?,
+            &RuleSet::default(),
+        )?)
+    };
+
+    assert!(matches!(
+        recognize("[C, [A, B, D]]")?,
+        Some(InferenceRule::Erasure(Erasure { target_atoms, .. })) if target_atoms.len() == 2
+    ));
+    assert!(matches!(
+        recognize("[A, B, C, [D]]")?,
+        Some(InferenceRule::Iteration(Iteration { backwards: true, parent_atoms, .. }))
+            if parent_atoms.len() == 2
+    ));
+    assert!(matches!(
+        recognize("[C, [[A, B]], [A, B, D]]")?,
+        Some(InferenceRule::DoubleCutDraw(DoubleCutDraw { target_atoms, .. }))
+            if target_atoms.len() == 2
+    ));
+    assert!(matches!(
+        recognize("[A, B, C, [A, B, C, D]]")?,
+        Some(InferenceRule::Iteration(Iteration {
+            backwards: false,
+            ..
+        }))
+    ));
+
+    // wide areas are looked at without listing every subset of them
+    let atoms = (0..40).map(|i| format!("A{}", i)).collect::<Vec<_>>();
+    let wide = Graph::try_from(format!("[{}]", atoms.join(",")).as_str())?;
+    let unreachable = Graph::try_from(format!("[[[{}]]]", atoms[1..].join(",")).as_str())?;
+    let recognize =
+        |after: &Graph| crate::proof::recognize::recognize(&wide, after, &RuleSet::default());
+    assert!(recognize(&Graph::try_from("[]")?)?.is_some());
+    assert!(recognize(&unreachable)?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn problem_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let problems = parse_problems(
+        "
+        # modus ponens
+        problem mp
+        premise [A]
+        premise [A, [A, [B]]]
+        goal [A, B]
+        end
+
+        problem absurd
+        premise [A, [A]]
+        goal contradiction
+        end
+        ",
+    )?;
+    assert_eq!(problems.len(), 2);
+    assert_eq!(problems[0].name(), "mp");
+
+    let reread = parse_problems(&write_problems(&problems))?;
+    assert_eq!(write_problems(&reread), write_problems(&problems));
+
+    // the premises share the sheet, so the A in them is only there once
+    let sheet = problems[0].sheet()?;
+    assert_eq!(
+        transform_graph_into_canonical_string(&sheet, sheet.root_id())?,
+        "[A,[A,[B]]]"
+    );
+
+    let proof = problems[0].start()?;
+    assert!(!proof.reaches(&problems[0])?);
+
+    let implication = *sheet.subgraphs_of(sheet.root_id())?.front().unwrap();
+    let proof = Proof::new(
+        sheet.clone(),
+        vec![
+            Arc::new(Iteration {
+                backwards: true,
+                parent: implication,
+                parent_atoms: vec![Arc::new(Atom::from("A"))],
+                parent_subgraphs: vec![],
+                target: *sheet.root_id(),
+            }),
+            Arc::new(DoubleCutErase {
+                target: implication,
+            }),
+        ],
+    );
+    assert!(proof.reaches(&problems[0])?);
+
+    // deiterating the inner A leaves an empty cut
+    let sheet = problems[1].sheet()?;
+    let cut = *sheet.subgraphs_of(sheet.root_id())?.front().unwrap();
+    let proof = Proof::new(
+        sheet.clone(),
+        vec![Arc::new(Iteration {
+            backwards: true,
+            parent: cut,
+            parent_atoms: vec![Arc::new(Atom::from("A"))],
+            parent_subgraphs: vec![],
+            target: *sheet.root_id(),
+        })],
+    );
+    assert!(matches!(problems[1].goal(), Goal::Contradiction));
+    assert!(proof.reaches(&problems[1])?);
+
+    assert!(matches!(
+        parse_problems("problem broken\npremise [A]\n"),
+        Err(ProofError::ProblemParseError(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn rule_set_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    assert_eq!(RuleSet::default(), RuleSet::peirce());
+    assert_eq!(
+        RuleSet::empty_double_cuts().double_cuts,
+        DoubleCuts::AroundNothing
+    );
+    assert!(!RuleSet::nested_iteration().iteration_within_area);
+
+    let rules = RuleSet::from_str("nested_iteration disabled=insertion")?;
+    assert_eq!(rules, RuleSet::nested_iteration().without("insertion"));
+    assert_eq!(RuleSet::from_str(&rules.to_string())?, rules);
+    assert!(RuleSet::from_str("hilbert").is_err());
+
+    // the textbook names are all Peirce's rules
+    for name in RuleSet::PRESETS {
+        assert!(RuleSet::preset(name).is_some());
+    }
+    assert_eq!(RuleSet::preset("roberts"), Some(RuleSet::peirce()));
+    assert_eq!(RuleSet::from_str("sowa")?, RuleSet::peirce());
+
+    let graph = Graph::try_from("[A,[B]]")?;
+    let cut = *graph.subgraphs_of(graph.root_id())?.front().unwrap();
+
+    // insertion is fine by default, but not once disabled
+    let insertion = InferenceRule::Insertion(Insertion {
+        target: cut,
+        new_content: "[C]".to_string(),
+    });
+    assert!(insertion.gen_actions_from_rule(&graph).is_ok());
+    assert!(matches!(
+        insertion.gen_actions_with_rules(&graph, &rules),
+        Err(ProofError::RuleDisabled(_))
+    ));
+
+    // double cuts can be restricted to empty ones
+    let draw = InferenceRule::DoubleCutDraw(DoubleCutDraw {
+        target: *graph.root_id(),
+        target_atoms: vec![Arc::new(Atom::from("A"))],
+        target_subgraphs: vec![],
+    });
+    assert!(draw
+        .gen_actions_with_rules(&graph, &RuleSet::peirce())
+        .is_ok());
+    assert!(matches!(
+        draw.gen_actions_with_rules(&graph, &RuleSet::empty_double_cuts()),
+        Err(ProofError::RestrictedByRuleSet { .. })
+    ));
+
+    let empty_draw = InferenceRule::DoubleCutDraw(DoubleCutDraw {
+        target: *graph.root_id(),
+        target_atoms: vec![],
+        target_subgraphs: vec![],
+    });
+    assert!(empty_draw
+        .gen_actions_with_rules(&graph, &RuleSet::empty_double_cuts())
+        .is_ok());
+
+    // and iteration to enclosed areas; a subgraph is iterated, since a copy of an atom couldn't
+    // go into the same area anyway
+    let within = InferenceRule::Iteration(Iteration {
+        backwards: false,
+        parent: *graph.root_id(),
+        parent_atoms: vec![],
+        parent_subgraphs: vec![cut],
+        target: *graph.root_id(),
+    });
+    let mut iterated = graph.clone();
+    Action::apply_actions(
+        within.gen_actions_with_rules(&graph, &RuleSet::peirce())?,
+        &mut iterated,
+    )?;
+    assert_eq!(
+        transform_graph_into_canonical_string(&iterated, iterated.root_id())?,
+        "[A,[B],[B]]"
+    );
+    assert!(matches!(
+        within.gen_actions_with_rules(&graph, &RuleSet::nested_iteration()),
+        Err(ProofError::RestrictedByRuleSet { .. })
+    ));
+
+    let inward = InferenceRule::Iteration(Iteration {
+        backwards: false,
+        parent: *graph.root_id(),
+        parent_atoms: vec![Arc::new(Atom::from("A"))],
+        parent_subgraphs: vec![],
+        target: cut,
+    });
+    assert!(inward
+        .gen_actions_with_rules(&graph, &RuleSet::nested_iteration())
+        .is_ok());
+
+    // listing, searching and recognizing only go by what the rule set allows
+    let listed = |rules: &RuleSet| InferenceRule::applicable(&graph, rules).collect::<Vec<_>>();
+    assert!(listed(&RuleSet::peirce()).contains(&within));
+    assert!(!listed(&RuleSet::nested_iteration()).contains(&within));
+    assert!(!listed(&RuleSet::peirce().without("insertion"))
+        .iter()
+        .any(|v| matches!(v, InferenceRule::Insertion(_))));
+    assert!(listed(&RuleSet::empty_double_cuts())
+        .iter()
+        .all(|v| !matches!(
+            v,
+            InferenceRule::DoubleCutDraw(DoubleCutDraw { target_atoms, target_subgraphs, .. })
+                if !target_atoms.is_empty() || !target_subgraphs.is_empty()
+        )));
+
+    let nested = Graph::try_from("[[[A]]]")?;
+    let unnested = Graph::try_from("[A]")?;
+    let limits = SearchLimits {
+        max_depth: 3,
+        ..Default::default()
+    };
+    let proof = search(&nested, &unnested, &RuleSet::nested_iteration(), &limits)?
+        .expect("proof should be found");
+    assert_eq!(proof.rule_set(), &RuleSet::nested_iteration());
+    let no_erasing = RuleSet::peirce().without("double_cut_erase");
+    assert!(search(&nested, &unnested, &no_erasing, &limits)?.is_none());
+    assert!(recognize(&nested, &unnested, &no_erasing)?.is_none());
+
+    // problems carry their rule set into their proofs
+    let problems = parse_problems(
+        "
+        problem restricted
+        premise [A]
+        goal [A,[[A]]]
+        rules empty_double_cuts
+        end
+        ",
+    )?;
+    assert_eq!(problems[0].rule_set(), &RuleSet::empty_double_cuts());
+    assert_eq!(
+        problems[0].start()?.rule_set(),
+        &RuleSet::empty_double_cuts()
+    );
+    assert_eq!(
+        parse_problems(&write_problems(&problems))?[0].rule_set(),
+        &RuleSet::empty_double_cuts()
+    );
+
+    let problems = parse_problems(
+        "
+        problem textbook
+        premise [A]
+        goal [A]
+        rules roberts
+        end
+        ",
+    )?;
+    assert_eq!(problems[0].rule_set(), &RuleSet::peirce());
+
+    Ok(())
+}
+
+#[test]
+fn latex_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let premise = Graph::try_from("[a_b,[B]]")?;
+    let cut = *premise.subgraphs_of(premise.root_id())?.front().unwrap();
+    let proof = Proof::new(
+        premise.clone(),
+        vec![
+            Arc::new(Insertion {
+                target: cut,
+                new_content: "[C]".to_string(),
+            }),
+            Arc::new(Erasure {
+                target_subgraphs: vec![cut],
+                target_atoms: vec![],
+            }),
+        ],
+    );
+
+    let latex = proof_to_latex(&proof)?;
+
+    assert!(latex.starts_with("\\documentclass"));
+    assert_eq!(latex.matches("\\item").count(), 3);
+    assert_eq!(latex.matches("\\begin{tikzpicture}").count(), 3);
+    assert!(latex.contains("{a\\_b}"));
+    assert!(latex.contains("\\textbf{insertion}"));
+    assert!(latex.contains(&format!("{{{}}}", cut.to_string().replace('_', "\\_"))));
+
+    Ok(())
+}
+
+#[test]
+fn svg_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let graph = Graph::try_from("[A,<B>,[C,[]]]")?;
+    let svg = graph_to_svg(&graph, graph.root_id(), &SvgOptions::default())?;
+
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.ends_with("</svg>\n"));
+    assert_eq!(svg.matches("rx=").count(), 2);
+    assert!(svg.contains(">A</text>"));
+    assert!(svg.contains(">&lt;B&gt;</text>"));
+
+    // the same graph is always drawn the same way
+    assert_eq!(
+        graph_to_svg(&graph, graph.root_id(), &SvgOptions::default())?,
+        svg
+    );
+
+    let cut = *graph.subgraphs_of(graph.root_id())?.front().unwrap();
+    let labelled = graph_to_svg(
+        &graph,
+        graph.root_id(),
+        &SvgOptions {
+            cut_shape: CutShape::Oval,
+            show_ids: true,
+        },
+    )?;
+    assert!(labelled.contains(&format!(">{}</text>", cut)));
+
+    Ok(())
+}
+
+#[test]
+fn layout_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    fn check(graph: &Graph, layout: &Layout) -> Result<(), Box<dyn Error>> {
+        let fresh = Layout::new(graph, graph.root_id(), Metrics::default())?;
+        assert_eq!(layout.placed(), fresh.placed());
+
+        for k in graph.subgraph_keys().filter(|k| *k != graph.root_id()) {
+            let rect = layout.cut(k).unwrap();
+            let parent = graph.parent_of(k)?;
+
+            if parent != graph.root_id() {
+                assert!(layout.cut(parent).unwrap().contains(&rect));
+            }
+
+            for e in graph.subgraphs_of(parent)?.iter().filter(|e| *e != k) {
+                assert!(!layout.cut(e).unwrap().overlaps(&rect));
+            }
+            for a in graph.atoms_of(parent)? {
+                let atom = layout.atom(parent, &a.to_string()).unwrap();
+                assert!(!atom.overlaps(&rect));
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut graph = Graph::try_from("[A,B,[C,[D,E]],[[F]]]")?;
+    let mut layout = Layout::new(&graph, graph.root_id(), Metrics::default())?;
+    check(&graph, &layout)?;
+
+    assert_eq!(
+        layout
+            .placed()
+            .iter()
+            .filter(|v| matches!(v, Placed::Cut { .. }))
+            .count(),
+        4
+    );
+
+    // relaying out after each action gives the same result as starting over
+    let [first, second] = graph
+        .subgraphs_of(graph.root_id())?
+        .iter()
+        .cloned()
+        .collect::<Vec<_>>()[..]
+    else {
+        panic!()
+    };
+    let inner = *graph.subgraphs_of(&first)?.front().unwrap();
+
+    for actions in [
+        vec![Action::AddAtom {
+            target: GraphTarget::Exists(inner),
+            atom: Atom::from("LONG"),
+        }],
+        vec![Action::AddSubgraph {
+            target: GraphTarget::Exists(inner),
+            new_subgraph: GraphTarget::Future(0),
+        }],
+        vec![Action::MoveSubgraph {
+            target: GraphTarget::Exists(inner),
+            dest: GraphTarget::Exists(second),
+        }],
+        vec![
+            Action::DeleteAtom {
+                target: GraphTarget::Exists(first),
+                atom: Atom::from("C"),
+            },
+            Action::DeleteSubgraph {
+                target: GraphTarget::Exists(first),
+            },
+        ],
+    ] {
+        let undo = Action::apply_actions(actions.clone(), &mut graph)?;
+        layout.relayout(&graph, &actions)?;
+        check(&graph, &layout)?;
+
+        // the undo actions work too
+        let mut undone = graph.clone();
+        let mut undone_layout = layout.clone();
+        Action::apply_actions(undo.clone(), &mut undone)?;
+        undone_layout.relayout(&undone, &undo)?;
+        check(&undone, &undone_layout)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn dot_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let graph = Graph::try_from("[A,[B,[]]]")?;
+    let cut = *graph.subgraphs_of(graph.root_id())?.front().unwrap();
+    let inner = *graph.subgraphs_of(&cut)?.front().unwrap();
+
+    let dot = graph_to_dot(&graph, graph.root_id())?;
+
+    assert!(dot.starts_with("digraph {"));
+    assert_eq!(dot.matches("subgraph ").count(), 2);
+    assert_eq!(dot.matches("fillcolor=gray85").count(), 1);
+    assert!(dot.contains(&format!("\"{}.B\" [label=\"B\"]", cut)));
+
+    // the empty cut still shows up, and is nested in its parent
+    let outer = dot.find(&format!("cluster_{}", cut)).unwrap();
+    let nested = dot.find(&format!("cluster_{}", inner)).unwrap();
+    assert!(outer < nested);
+    assert!(dot.contains(&format!("\"{}\" [label=\"\", shape=point", inner)));
+
+    Ok(())
+}
+
+#[test]
+fn text_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let graph = Graph::try_from("[B,A,[C,[]]]")?;
+    let text = graph_to_text(
+        &graph,
+        graph.root_id(),
+        &TextOptions {
+            width: 80,
+            show_ids: false,
+        },
+    )?;
+
+    assert_eq!(
+        text,
+        [
+            "A B ╭────────╮",
+            "    │ C ╭──╮ │",
+            "    │   ╰──╯ │",
+            "    ╰────────╯",
+            "",
+        ]
+        .join("\n")
+    );
+
+    // with the ids on, every cut is labelled
+    let cut = *graph.subgraphs_of(graph.root_id())?.front().unwrap();
+    let text = graph_to_text(&graph, graph.root_id(), &TextOptions::default())?;
+    assert!(text.contains(&format!("╭─{}", cut)));
+
+    // things that don't fit wrap onto the next row
+    let graph = Graph::try_from("[AAAA,BBBB,CCCC]")?;
+    let text = graph_to_text(
+        &graph,
+        graph.root_id(),
+        &TextOptions {
+            width: 9,
+            show_ids: false,
+        },
+    )?;
+    assert_eq!(text, "AAAA BBBB\nCCCC\n");
+
+    Ok(())
+}
+
+#[test]
+fn syntax_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let graph = Graph::try_from("[B, A, [A, [B]], [[C], [D]], []]")?;
+    let id = graph.root_id();
+
+    assert_eq!(
+        write_graph(&graph, id, Syntax::Parenthesis)?,
+        "(A,B,(A,(B)),((C),(D)),())"
+    );
+    assert_eq!(
+        write_graph(&graph, id, Syntax::Egif)?,
+        "(A) (B) ~[ (A) ~[ (B) ] ] ~[ ~[ (C) ] ~[ (D) ] ] ~[ ]"
+    );
+    assert_eq!(
+        write_graph(&graph, id, Syntax::Formula)?,
+        "A & B & (A -> B) & (C | D) & false"
+    );
+
+    // every syntax reads back what it writes
+    let canonical = transform_graph_into_canonical_string(&graph, id)?;
+    for syntax in [
+        Syntax::Bracket,
+        Syntax::Parenthesis,
+        Syntax::Egif,
+        Syntax::Formula,
+    ] {
+        let reread = read_graph(&write_graph(&graph, id, syntax)?, syntax)?;
+        assert_eq!(
+            transform_graph_into_canonical_string(&reread, reread.root_id())?,
+            canonical
+        );
+    }
+
+    // formulas are read with the usual precedence
+    let graph = read_graph("!A | B & C -> D", Syntax::Formula)?;
+    let expected = Graph::try_from("[[[[[A]],[B,C]],[D]]]")?;
+    assert_eq!(
+        transform_graph_into_canonical_string(&graph, graph.root_id())?,
+        transform_graph_into_canonical_string(&expected, expected.root_id())?
+    );
+    let graph = Graph::new();
+    assert_eq!(
+        write_graph(&graph, graph.root_id(), Syntax::Formula)?,
+        "true"
+    );
+
+    assert!(read_graph("A &", Syntax::Formula).is_err());
+    assert!(read_graph("(A) ~[ (B)", Syntax::Egif).is_err());
+    assert!(read_graph("(A", Syntax::Egif).is_err());
+    assert!(read_graph("~[ (A ]", Syntax::Egif).is_err());
+    assert!(Syntax::from_str("latin").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn semantics_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    for (graph, valid, satisfiable) in [
+        ("[]", true, true),
+        ("[[]]", false, false),
+        ("[A]", false, true),
+        ("[[A, [A]]]", true, true),
+        ("[A, [A]]", false, false),
+        ("[[A], [B]]", false, true),
+        ("[[A], [B], [[A], [B]]]", false, false),
+        ("[[[A], [[A]]]]", true, true),
+    ] {
+        let graph = Graph::try_from(graph)?;
+        let ans = check(&graph, graph.root_id())?;
+
+        assert_eq!(ans.valid, valid);
+        assert_eq!(ans.satisfiable(), satisfiable);
+        assert_eq!(ans.counterexample.is_none(), valid);
+    }
+
+    let graph = Graph::try_from("[A, [B]]")?;
+    let ans = check(&graph, graph.root_id())?;
+    assert_eq!(ans.model, Some(["A".to_string()].into()));
+
+    Ok(())
+}
+
+#[test]
+fn proof_file_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let premise = Graph::try_from("[A, [A, [B]]]")?;
+    let goal = Graph::try_from("[A, B]")?;
+    let proof = search(
+        &premise,
+        &goal,
+        &RuleSet::default(),
+        &SearchLimits::default(),
+    )?
+    .expect("proof should be found")
+    .with_rule_set(RuleSet::from_str("disabled=insertion")?);
+
+    let text = proof.to_string();
+    assert!(text.starts_with("premise "));
+    assert!(text.contains("rules "));
+
+    let reread = Proof::from_str(&text)?;
+    assert_eq!(reread.to_string(), text);
+    assert_eq!(reread.rule_set(), proof.rule_set());
+
+    let conclusion = reread.conclusion()?;
+    assert_eq!(
+        transform_graph_into_canonical_string(&conclusion, conclusion.root_id())?,
+        transform_graph_into_canonical_string(&goal, goal.root_id())?
+    );
+
+    assert!(Proof::from_str("step whatever").is_err());
+    assert!(Proof::from_str("").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn action_format_test() -> Result<(), Box<dyn Error>> {
+    init_logging()?;
+
+    let mut graph = Graph::try_from("[A, [B]]")?;
+    let root = *graph.root_id();
+    let cut = *graph.subgraphs_of(&root)?.front().unwrap();
+
+    let actions = [
+        Action::AddAtom {
+            target: GraphTarget::Exists(cut),
+            atom: "long name".into(),
+        },
+        Action::AddSubgraph {
+            target: GraphTarget::Exists(root),
+            new_subgraph: GraphTarget::Future(0),
+        },
+        Action::MoveSubgraph {
+            target: GraphTarget::Exists(cut),
+            dest: GraphTarget::Future(0),
+        },
+    ];
+
+    assert_eq!(
+        actions.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+        [
+            format!("add-atom {} long name", cut),
+            format!("add-cut {} #0", root),
+            format!("move-cut {} #0", cut),
+        ]
+    );
+
+    // the undo actions survive being written out and read back
+    let before = transform_graph_into_keyed_string(&graph, &root)?;
+    let undo = Action::apply_actions(
+        actions
+            .iter()
+            .map(|v| Action::from_str(&v.to_string()))
+            .collect::<Result<Vec<_>, _>>()?,
+        &mut graph,
+    )?;
+    let undo = undo
+        .iter()
+        .map(|v| Action::from_str(&v.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    Action::apply_actions(undo, &mut graph)?;
+    assert_eq!(transform_graph_into_keyed_string(&graph, &root)?, before);
+
+    assert!(Action::from_str("add-atom").is_err());
+    assert!(Action::from_str("delete-cut #x").is_err());
+    assert!(Action::from_str("delete-cut #0 extra").is_err());
+    assert!(Action::from_str("paint #0").is_err());
+
+    Ok(())
+}
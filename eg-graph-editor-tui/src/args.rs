@@ -1,9 +1,26 @@
 pub mod edit;
 pub mod mode;
 pub mod rule;
+pub mod task;
 
-use self::{edit::EditCommand, mode::Mode, rule::RuleCommand};
+use self::{edit::EditCommand, mode::Mode, rule::RuleCommand, task::Task};
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+/// how the program itself is started
+#[derive(Parser)]
+pub struct Options {
+    ///Runs the commands in a file instead of prompting for them; piped input is run the same way
+    #[arg(long)]
+    pub script: Option<PathBuf>,
+
+    ///Carries on with the rest of a script after a command fails
+    #[arg(long)]
+    pub keep_going: bool,
+
+    #[command(subcommand)]
+    pub task: Option<Task>,
+}
 
 #[derive(Parser)]
 #[command(disable_help_flag = true)]
@@ -16,7 +33,48 @@ pub struct Cli {
 pub enum Command {
     ///Shows current graph
     #[command(visible_alias = "s")]
-    Show,
+    Show {
+        ///List every area on its own line instead of drawing the cuts
+        #[arg(long)]
+        list: bool,
+
+        ///Leave the ids off the cuts
+        #[arg(long)]
+        no_ids: bool,
+    },
+
+    ///Opens a full-screen view of the graph to navigate and apply rules in
+    #[command(visible_alias = "f")]
+    Screen,
+
+    ///Lists the rules that can be applied to the current graph
+    Suggest {
+        ///The most rules to list; double cuts alone can be drawn in more ways than fit on screen
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+
+    ///Writes the current graph to an SVG file
+    Svg {
+        path: String,
+
+        ///Label every cut with its id
+        #[arg(long)]
+        ids: bool,
+    },
+
+    ///Writes the current graph to a Graphviz DOT file
+    Dot { path: String },
+
+    ///Loads a problem from a file, replacing the graph with its premises and switching to proof mode
+    #[command(visible_alias = "lp")]
+    Problem { path: String, name: Option<String> },
+
+    ///Saves the graph with its ids, the mode and the undo history to a file
+    Save { path: PathBuf },
+
+    ///Resumes a session saved with save, replacing the current one
+    Open { path: PathBuf },
 
     ///Exits
     #[command(visible_alias = "q")]
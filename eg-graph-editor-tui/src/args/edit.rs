@@ -18,6 +18,14 @@ pub enum EditCommand {
     #[command(visible_alias = "dc")]
     DeleteCut { target: String },
 
+    /// Rename every occurrence of an atom
+    #[command(visible_alias = "ra")]
+    RenameAtom { from: String, to: String },
+
+    /// Replace every occurrence of an atom with the contents of <with>
+    #[command(visible_alias = "sub")]
+    Substitute { atom: String, with: String },
+
     ///Load a new subgraph
     /// WARNING: WILL OVERWRITE THE OLD GRAPH
     #[command(visible_alias = "l")]
@@ -23,7 +23,8 @@ pub enum RuleCommand {
         /// target subgraph to insert in
         target: String,
 
-        /// string representation of what to insert; needs to be valid graph syntax
+        /// string representation of what to insert; needs to be valid graph syntax, with spaces and
+        /// `[ ] , : %` in atom names written as `%XX` escapes
         new_content: String,
     },
     #[command(visible_alias = "ea")]
@@ -0,0 +1,80 @@
+use clap::{Subcommand, ValueEnum};
+use eg_graph_editor_lib::graph::syntax::Syntax;
+use std::path::PathBuf;
+
+/// a one-shot job run instead of the REPL; inputs default to stdin
+#[derive(Subcommand)]
+pub enum Task {
+    ///Rewrites a graph from one syntax into another
+    Convert {
+        #[arg(long, value_enum, default_value_t = Format::Bracket)]
+        from: Format,
+
+        #[arg(long, value_enum, default_value_t = Format::Bracket)]
+        to: Format,
+
+        input: Option<PathBuf>,
+    },
+
+    ///Reports whether a graph is valid and whether it is satisfiable
+    Check {
+        #[arg(long, value_enum, default_value_t = Format::Bracket)]
+        from: Format,
+
+        input: Option<PathBuf>,
+    },
+
+    ///Replays a proof file, exiting with an error if any step is invalid
+    Verify {
+        proof: PathBuf,
+
+        ///Also require the proof to start from the premises of a problem in this file and reach its goal
+        #[arg(long)]
+        problem: Option<PathBuf>,
+
+        ///Which problem in the file to check against; defaults to the first
+        #[arg(long, requires = "problem")]
+        name: Option<String>,
+    },
+
+    ///Draws a graph as SVG, DOT or text
+    Render {
+        #[arg(long, value_enum)]
+        format: RenderFormat,
+
+        #[arg(long, value_enum, default_value_t = Format::Bracket)]
+        from: Format,
+
+        ///Label every cut with its id
+        #[arg(long)]
+        ids: bool,
+
+        input: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum Format {
+    Bracket,
+    Parenthesis,
+    Egif,
+    Formula,
+}
+
+impl From<Format> for Syntax {
+    fn from(value: Format) -> Self {
+        match value {
+            Format::Bracket => Syntax::Bracket,
+            Format::Parenthesis => Syntax::Parenthesis,
+            Format::Egif => Syntax::Egif,
+            Format::Formula => Syntax::Formula,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum RenderFormat {
+    Svg,
+    Dot,
+    Text,
+}
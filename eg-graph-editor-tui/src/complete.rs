@@ -0,0 +1,109 @@
+use crate::args::Cli;
+use clap::{Command, CommandFactory};
+use eg_graph_editor_lib::graph::Graph;
+use itertools::Itertools;
+use rustyline::{
+    completion::{Completer, Pair},
+    highlight::Highlighter,
+    hint::Hinter,
+    validate::Validator,
+    Context, Helper,
+};
+
+/// completes command names and aliases, and the ids and atoms of the current graph
+pub struct CommandHelper {
+    command: Command,
+    graph_words: Vec<String>,
+}
+
+impl CommandHelper {
+    pub fn new() -> Self {
+        Self {
+            command: Cli::command(),
+            graph_words: Vec::new(),
+        }
+    }
+
+    /// picks up the ids and atoms of `graph`; called before every prompt
+    pub fn update(&mut self, graph: &Graph) {
+        self.graph_words = graph
+            .subgraph_keys()
+            .map(|k| k.to_string())
+            .chain(graph.atoms().map(|a| a.to_string()))
+            .sorted()
+            .dedup()
+            .collect();
+    }
+}
+
+/// the names and visible aliases of the subcommands of `command`
+fn subcommand_names(command: &Command) -> Vec<String> {
+    command
+        .get_subcommands()
+        .flat_map(|v| {
+            std::iter::once(v.get_name().to_string())
+                .chain(v.get_visible_aliases().map(|a| a.to_string()))
+        })
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+impl Default for CommandHelper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Completer for CommandHelper {
+    type Candidate = Pair;
+
+    /// the first word can only be a command and the word after a command with subcommands one
+    /// of those; later ones are ids and atoms
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .rfind(char::is_whitespace)
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let word = &line[start..pos];
+
+        // follows the words so far down the subcommands; once one isn't a subcommand, the rest are
+        // arguments
+        let mut command = Some(&self.command);
+        for v in line[..start].split_whitespace() {
+            command = command.and_then(|c| c.find_subcommand(v));
+        }
+
+        let words = match command {
+            Some(c) if c.has_subcommands() => subcommand_names(c),
+            _ => self.graph_words.clone(),
+        };
+
+        Ok((
+            start,
+            words
+                .into_iter()
+                .filter(|v| v.starts_with(word))
+                .map(|v| Pair {
+                    display: v.clone(),
+                    replacement: v,
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl Hinter for CommandHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CommandHelper {}
+
+impl Validator for CommandHelper {}
+
+impl Helper for CommandHelper {}
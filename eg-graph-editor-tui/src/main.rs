@@ -1,13 +1,22 @@
 pub mod args;
+pub mod complete;
 pub mod print;
+pub mod screen;
+pub mod session;
+pub mod task;
+
+#[cfg(test)]
+mod test;
 
 use crate::{
     args::{edit::EditCommand, mode::Mode},
+    complete::CommandHelper,
     print::print_graph,
+    screen::Screen,
+    session::Session,
 };
-use args::{rule::RuleCommand, Cli, Command};
-use async_std::io::{stdin, stdout, ReadExt, WriteExt};
-use clap::{builder::styling::AnsiColor, Parser};
+use args::{rule::RuleCommand, Cli, Command, Options};
+use clap::{builder::styling::AnsiColor, error::ErrorKind, Parser};
 use color_eyre::{
     eyre::{eyre, Error},
     owo_colors::AnsiColors,
@@ -18,11 +27,33 @@ use eg_graph_editor_lib::{
     graph::{Graph, GraphKey},
     proof::{
         action::{Action, GraphTarget},
-        inference_rule::InferenceRule,
+        inference_rule::{
+            DoubleCutDraw, DoubleCutErase, Erasure, InferenceRule, Insertion, Iteration,
+        },
+        problem::parse_problems,
+        rule::Rule,
+        rule_set::RuleSet,
+    },
+    render::{
+        dot::graph_to_dot,
+        svg::{graph_to_svg, SvgOptions},
+        text::{graph_to_text, TextOptions},
     },
 };
 use fallible_iterator::{FallibleIterator, IteratorExt};
-use std::{collections::VecDeque, env, mem, sync::Arc};
+use rustyline::{error::ReadlineError, history::DefaultHistory, Editor};
+use std::{
+    collections::{HashMap, VecDeque},
+    env,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, IsTerminal},
+    iter, mem,
+    path::PathBuf,
+    process,
+    str::FromStr,
+    sync::Arc,
+};
+use terminal_size::{terminal_size, Width};
 use tracing::level_filters::LevelFilter;
 use tracing_error::ErrorLayer;
 use tracing_panic::panic_hook;
@@ -60,30 +91,187 @@ fn init_logging() -> Result<()> {
 async fn main() -> Result<()> {
     init_logging()?;
 
+    let options = Options::parse();
+
+    if let Some(task) = options.task {
+        return task::run(task);
+    }
+
     let mut graph = Graph::new();
     let mut undo_stack = VecDeque::new();
     let mut redo_stack = VecDeque::new();
 
     let mut current_mode = Mode::Editor;
+    let mut problem = None;
+    let mut rule_set = RuleSet::default();
+
+    // commands come from a script or a pipe without prompting
+    let mut script: Option<Box<dyn Iterator<Item = io::Result<String>>>> = match &options.script {
+        Some(path) => Some(Box::new(BufReader::new(File::open(path)?).lines())),
+        None if !io::stdin().is_terminal() => Some(Box::new(io::stdin().lines())),
+        None => None,
+    };
+
+    let mut editor = Editor::<CommandHelper, DefaultHistory>::new()?;
+    editor.set_helper(Some(CommandHelper::new()));
+
+    let history = history_path().filter(|_| script.is_none());
+    if let Some(path) = &history {
+        // there is no history yet the first time around
+        let _ = editor.load_history(path);
+    }
+
+    let mut line_number = 0;
+    let mut failed = false;
 
     loop {
-        print!("> ");
-        stdout().flush().await?;
+        let line = match &mut script {
+            Some(lines) => match lines.next() {
+                Some(line) => line?,
+                None => break,
+            },
+            None => {
+                if let Some(helper) = editor.helper_mut() {
+                    helper.update(&graph);
+                }
+
+                match editor.readline("> ") {
+                    Ok(line) => {
+                        editor.add_history_entry(line.as_str())?;
+                        line
+                    }
+                    Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+                    Err(x) => Err(x)?,
+                }
+            }
+        };
+        line_number += 1;
 
-        let mut line = String::new();
-        let _ = stdin().read_line(&mut line).await?;
+        // scripts can have blank lines and comments
+        if script.is_some() && (line.trim().is_empty() || line.trim_start().starts_with('#')) {
+            continue;
+        }
 
-        let args = Cli::try_parse_from(("> ".to_string() + &line).split_whitespace());
+        // quotes and backslashes work like in a shell, so graphs can contain spaces
+        let args = shell_words::split(&line)
+            .map(|words| Cli::try_parse_from(iter::once("> ".to_string()).chain(words)));
 
-        let res: Result<bool> = (|| match args {
+        let res: Result<bool> = (|| match args? {
             Ok(args) => match args.cmd {
-                Command::Show => {
+                Command::Show { list, no_ids } => {
                     println!();
-                    print_graph(&graph)?;
+                    if list {
+                        print_graph(&graph)?;
+                    } else {
+                        print!(
+                            "{}",
+                            graph_to_text(
+                                &graph,
+                                graph.root_id(),
+                                &TextOptions {
+                                    width: terminal_size()
+                                        .map(|(Width(w), _)| w as usize)
+                                        .unwrap_or(80),
+                                    show_ids: !no_ids,
+                                },
+                            )?
+                        );
+                    }
                     println!();
                     Ok(true)
                 }
 
+                Command::Screen => {
+                    Screen::new(
+                        &mut graph,
+                        &mut undo_stack,
+                        &mut redo_stack,
+                        &current_mode,
+                        &rule_set,
+                        problem.as_ref(),
+                    )?
+                    .run()?;
+                    Ok(true)
+                }
+
+                Command::Suggest { limit } => {
+                    let mut rules = InferenceRule::applicable(&graph, &rule_set);
+                    for rule in rules.by_ref().take(limit) {
+                        println!("{}", rule.describe());
+                    }
+                    if rules.next().is_some() {
+                        println!("... and more; raise --limit to see them");
+                    }
+                    Ok(true)
+                }
+
+                Command::Svg { path, ids } => {
+                    fs::write(
+                        path,
+                        graph_to_svg(
+                            &graph,
+                            graph.root_id(),
+                            &SvgOptions {
+                                show_ids: ids,
+                                ..Default::default()
+                            },
+                        )?,
+                    )?;
+                    Ok(true)
+                }
+
+                Command::Dot { path } => {
+                    fs::write(path, graph_to_dot(&graph, graph.root_id())?)?;
+                    Ok(true)
+                }
+
+                Command::Problem { path, name } => {
+                    let mut problems = parse_problems(&fs::read_to_string(path)?)?.into_iter();
+
+                    let new_problem = match name {
+                        Some(name) => problems
+                            .find(|v| v.name() == name)
+                            .ok_or_else(|| eyre!("No problem named {}", name))?,
+                        None => problems
+                            .next()
+                            .ok_or_else(|| eyre!("No problems in file"))?,
+                    };
+
+                    graph = new_problem.sheet()?;
+                    undo_stack.clear();
+                    redo_stack.clear();
+                    rule_set = new_problem.rule_set().clone();
+                    problem = Some(new_problem);
+                    current_mode = Mode::Proof;
+                    Ok(true)
+                }
+
+                Command::Save { path } => {
+                    let session = Session {
+                        graph: graph.clone(),
+                        mode: current_mode.clone(),
+                        rule_set: rule_set.clone(),
+                        problem: problem.clone(),
+                        undo_stack: undo_stack.clone(),
+                        redo_stack: redo_stack.clone(),
+                    };
+
+                    fs::write(path, session.to_string())?;
+                    Ok(true)
+                }
+
+                Command::Open { path } => {
+                    let session = Session::from_str(&fs::read_to_string(path)?)?;
+
+                    graph = session.graph;
+                    current_mode = session.mode;
+                    rule_set = session.rule_set;
+                    problem = session.problem;
+                    undo_stack = session.undo_stack;
+                    redo_stack = session.redo_stack;
+                    Ok(true)
+                }
+
                 Command::Exit => Ok(false),
 
                 Command::Mode { mode } => {
@@ -102,47 +290,60 @@ async fn main() -> Result<()> {
                     }
 
                     match how {
-                        EditCommand::NewAtom { target, atom } => undo_stack.push_front(
-                            Action::apply_actions(
+                        EditCommand::NewAtom { target, atom } => {
+                            undo_stack.push_front(Action::apply_actions(
                                 [Action::AddAtom {
                                     target: GraphTarget::Exists(GraphKey::try_from_str(&target)?),
                                     atom: atom.into(),
                                 }],
                                 &mut graph,
-                            )
-                            .unwrap(),
-                        ),
+                            )?)
+                        }
 
-                        EditCommand::NewCut { target } => undo_stack.push_front(
-                            Action::apply_actions(
+                        EditCommand::NewCut { target } => {
+                            undo_stack.push_front(Action::apply_actions(
                                 [Action::AddSubgraph {
                                     target: GraphTarget::Exists(GraphKey::try_from_str(&target)?),
                                     new_subgraph: GraphTarget::Future(0),
                                 }],
                                 &mut graph,
-                            )
-                            .unwrap(),
-                        ),
+                            )?)
+                        }
 
-                        EditCommand::DeleteAtom { target, atom } => undo_stack.push_front(
-                            Action::apply_actions(
+                        EditCommand::DeleteAtom { target, atom } => {
+                            undo_stack.push_front(Action::apply_actions(
                                 [Action::DeleteAtom {
                                     target: GraphTarget::Exists(GraphKey::try_from_str(&target)?),
                                     atom: atom.into(),
                                 }],
                                 &mut graph,
-                            )
-                            .unwrap(),
-                        ),
-                        EditCommand::DeleteCut { target } => undo_stack.push_front(
-                            Action::apply_actions(
+                            )?)
+                        }
+                        EditCommand::DeleteCut { target } => {
+                            undo_stack.push_front(Action::apply_actions(
                                 [Action::DeleteSubgraph {
                                     target: GraphTarget::Exists(GraphKey::try_from_str(&target)?),
                                 }],
                                 &mut graph,
-                            )
-                            .unwrap(),
-                        ),
+                            )?)
+                        }
+
+                        EditCommand::RenameAtom { from, to } => {
+                            undo_stack.push_front(Action::apply_actions(
+                                graph.rename_atom(&from.into(), to.into())?,
+                                &mut graph,
+                            )?)
+                        }
+
+                        EditCommand::Substitute { atom, with } => {
+                            undo_stack.push_front(Action::apply_actions(
+                                graph.substitute(&HashMap::from([(
+                                    atom.into(),
+                                    Graph::try_from(with.as_str())?,
+                                )]))?,
+                                &mut graph,
+                            )?)
+                        }
 
                         EditCommand::Load { new_graph } => {
                             let mut new_graph = Graph::try_from(new_graph.as_str())?;
@@ -162,8 +363,8 @@ async fn main() -> Result<()> {
                             target,
                             target_atoms,
                             target_subgraphs,
-                        } => redo_stack.push_front(Action::apply_actions(
-                            InferenceRule::DoubleCutDraw {
+                        } => undo_stack.push_front(Action::apply_actions(
+                            InferenceRule::DoubleCutDraw(DoubleCutDraw {
                                 target: GraphKey::try_from_str(target.as_str())?,
                                 target_atoms: target_atoms
                                     .into_iter()
@@ -174,17 +375,17 @@ async fn main() -> Result<()> {
                                     .map(|v| Ok::<_, Report>(GraphKey::try_from_str(v.as_str())?))
                                     .transpose_into_fallible()
                                     .collect()?,
-                            }
-                            .gen_actions_from_rule(&graph)?,
+                            })
+                            .gen_actions_with_rules(&graph, &rule_set)?,
                             &mut graph,
                         )?),
 
                         RuleCommand::DoubleCutErase { target } => {
-                            redo_stack.push_front(Action::apply_actions(
-                                InferenceRule::DoubleCutErase {
+                            undo_stack.push_front(Action::apply_actions(
+                                InferenceRule::DoubleCutErase(DoubleCutErase {
                                     target: GraphKey::try_from_str(target.as_str())?,
-                                }
-                                .gen_actions_from_rule(&graph)?,
+                                })
+                                .gen_actions_with_rules(&graph, &rule_set)?,
                                 &mut graph,
                             )?)
                         }
@@ -192,12 +393,12 @@ async fn main() -> Result<()> {
                         RuleCommand::Insertion {
                             target,
                             new_content,
-                        } => redo_stack.push_front(Action::apply_actions(
-                            InferenceRule::Insertion {
+                        } => undo_stack.push_front(Action::apply_actions(
+                            InferenceRule::Insertion(Insertion {
                                 target: GraphKey::try_from_str(target.as_str())?,
                                 new_content,
-                            }
-                            .gen_actions_from_rule(&graph)?,
+                            })
+                            .gen_actions_with_rules(&graph, &rule_set)?,
                             &mut graph,
                         )?),
 
@@ -207,22 +408,22 @@ async fn main() -> Result<()> {
                         } => {
                             let parent_subgraph = GraphKey::try_from_str(parent_subgraph.as_str())?;
 
-                            redo_stack.push_front(Action::apply_actions(
-                                InferenceRule::Erasure {
+                            undo_stack.push_front(Action::apply_actions(
+                                InferenceRule::Erasure(Erasure {
                                     target_subgraphs: vec![],
                                     target_atoms: atoms
                                         .into_iter()
                                         .map(|v| (parent_subgraph, Arc::new(Atom::new(v))))
                                         .collect(),
-                                }
-                                .gen_actions_from_rule(&graph)?,
+                                })
+                                .gen_actions_with_rules(&graph, &rule_set)?,
                                 &mut graph,
                             )?)
                         }
 
                         RuleCommand::ErasureCuts { target_subgraphs } => {
-                            redo_stack.push_front(Action::apply_actions(
-                                InferenceRule::Erasure {
+                            undo_stack.push_front(Action::apply_actions(
+                                InferenceRule::Erasure(Erasure {
                                     target_subgraphs: target_subgraphs
                                         .into_iter()
                                         .map(|v| {
@@ -231,8 +432,8 @@ async fn main() -> Result<()> {
                                         .transpose_into_fallible()
                                         .collect()?,
                                     target_atoms: vec![],
-                                }
-                                .gen_actions_from_rule(&graph)?,
+                                })
+                                .gen_actions_with_rules(&graph, &rule_set)?,
                                 &mut graph,
                             )?)
                         }
@@ -242,8 +443,8 @@ async fn main() -> Result<()> {
                             // parent_atoms,
                             parent_subgraphs,
                             target,
-                        } => redo_stack.push_front(Action::apply_actions(
-                            InferenceRule::Iteration {
+                        } => undo_stack.push_front(Action::apply_actions(
+                            InferenceRule::Iteration(Iteration {
                                 backwards: false,
                                 parent: GraphKey::try_from_str(parent.as_str())?,
                                 parent_atoms: vec![],
@@ -253,8 +454,8 @@ async fn main() -> Result<()> {
                                     .transpose_into_fallible()
                                     .collect()?,
                                 target: GraphKey::try_from_str(target.as_str())?,
-                            }
-                            .gen_actions_from_rule(&graph)?,
+                            })
+                            .gen_actions_with_rules(&graph, &rule_set)?,
                             &mut graph,
                         )?),
                         RuleCommand::Iteration_g {
@@ -262,15 +463,15 @@ async fn main() -> Result<()> {
                             parent_atoms,
                             // parent_subgraphs,
                             target,
-                        } => redo_stack.push_front(Action::apply_actions(
-                            InferenceRule::Iteration {
+                        } => undo_stack.push_front(Action::apply_actions(
+                            InferenceRule::Iteration(Iteration {
                                 backwards: false,
                                 parent: GraphKey::try_from_str(parent.as_str())?,
                                 parent_atoms: vec![],
                                 parent_subgraphs: vec![],
                                 target: GraphKey::try_from_str(target.as_str())?,
-                            }
-                            .gen_actions_from_rule(&graph)?,
+                            })
+                            .gen_actions_with_rules(&graph, &rule_set)?,
                             &mut graph,
                         )?),
                     };
@@ -281,7 +482,7 @@ async fn main() -> Result<()> {
                 Command::Undo { times } => {
                     let mut iter = 0;
                     while let Some(things) = undo_stack.pop_front() {
-                        redo_stack.push_front(Action::apply_actions(things, &mut graph).unwrap());
+                        redo_stack.push_front(Action::apply_actions(things, &mut graph)?);
                         iter += 1;
                         if iter >= times {
                             break;
@@ -292,7 +493,7 @@ async fn main() -> Result<()> {
                 Command::Redo { times } => {
                     let mut iter = 0;
                     while let Some(things) = redo_stack.pop_front() {
-                        undo_stack.push_front(Action::apply_actions(things, &mut graph).unwrap());
+                        undo_stack.push_front(Action::apply_actions(things, &mut graph)?);
                         iter += 1;
                         if iter >= times {
                             break;
@@ -301,18 +502,48 @@ async fn main() -> Result<()> {
                     Ok(true)
                 }
             },
-            Err(x) => {
+            Err(x) if matches!(x.kind(), ErrorKind::DisplayHelp | ErrorKind::DisplayVersion) => {
                 println!("{}", x);
                 Ok(true)
             }
+            Err(x) => Err(x)?,
         })();
 
-        if let Err(x) = res {
-            println!("{}", x);
-        } else if let Ok(false) = res {
-            break;
-        };
+        if let (Ok(true), Some(problem)) = (&res, &problem) {
+            if let Ok(true) = problem.is_reached_by(&graph) {
+                println!("Goal reached");
+            }
+        }
+
+        match res {
+            Err(x) if script.is_some() => {
+                eprintln!("line {}: {}", line_number, x);
+                failed = true;
+
+                if !options.keep_going {
+                    break;
+                }
+            }
+            Err(x) => println!("{}", x),
+            Ok(false) => break,
+            Ok(true) => {}
+        }
+    }
+
+    if let Some(path) = &history {
+        editor.save_history(path)?;
+    }
+
+    if failed {
+        process::exit(1);
     }
 
     Ok(())
 }
+
+/// where the prompt history is kept between sessions
+fn history_path() -> Option<PathBuf> {
+    env::var_os("EG_HISTORY")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|v| PathBuf::from(v).join(".eg_graph_editor_history")))
+}
@@ -9,9 +9,9 @@ pub fn print_graph(graph: &Graph) -> Result<()> {
     queue.push_back(graph.root_id());
 
     while let Some(id) = queue.pop_front() {
-        let lvl = graph.level_of(id).unwrap();
-        let atoms = graph.atoms_of(id).unwrap();
-        let children = graph.subgraphs_of(id).unwrap();
+        let lvl = graph.level_of(id)?;
+        let atoms = graph.atoms_of(id)?;
+        let children = graph.subgraphs_of(id)?;
 
         println!(
             "  {}{}: Atoms [{}]",
@@ -0,0 +1,516 @@
+use crate::args::mode::Mode;
+use color_eyre::{eyre::eyre, Result};
+use eg_graph_editor_lib::{
+    atom::Atom,
+    graph::{Graph, GraphKey},
+    proof::{
+        action::{Action, GraphTarget},
+        inference_rule::{InferenceRule, Insertion},
+        problem::Problem,
+        rule::Rule,
+        rule_set::RuleSet,
+    },
+};
+use itertools::Itertools;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+use std::{
+    collections::{LinkedList, VecDeque},
+    sync::Arc,
+};
+
+/// a line of the tree view
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Entry {
+    Area(GraphKey),
+    Atom(GraphKey, String),
+}
+
+impl Entry {
+    /// the area the entry sits in
+    fn parent(&self, graph: &Graph) -> Result<GraphKey> {
+        Ok(match self {
+            Entry::Area(k) => *graph.parent_of(k)?,
+            Entry::Atom(k, _) => *k,
+        })
+    }
+
+    /// the area the entry is, or sits in
+    fn area(&self) -> GraphKey {
+        match self {
+            Entry::Area(k) | Entry::Atom(k, _) => *k,
+        }
+    }
+}
+
+/// what the text typed at the bottom is for
+#[derive(Debug, Clone, Copy)]
+enum Prompt {
+    Insert(GraphKey),
+    AddAtom(GraphKey),
+}
+
+/// the full-screen interface, working on the same graph and undo history as the prompt
+pub struct Screen<'a> {
+    graph: &'a mut Graph,
+    undo_stack: &'a mut VecDeque<LinkedList<Action>>,
+    redo_stack: &'a mut VecDeque<LinkedList<Action>>,
+    mode: &'a Mode,
+    rule_set: &'a RuleSet,
+    problem: Option<&'a Problem>,
+
+    entries: Vec<Entry>,
+    tree: ListState,
+    selected: Vec<Entry>,
+
+    /// the rules that can be applied to the selection, while choosing one
+    menu: Option<(Vec<InferenceRule>, ListState)>,
+    prompt: Option<(Prompt, String)>,
+
+    status: Result<String>,
+    history: Vec<String>,
+}
+
+impl<'a> Screen<'a> {
+    pub fn new(
+        graph: &'a mut Graph,
+        undo_stack: &'a mut VecDeque<LinkedList<Action>>,
+        redo_stack: &'a mut VecDeque<LinkedList<Action>>,
+        mode: &'a Mode,
+        rule_set: &'a RuleSet,
+        problem: Option<&'a Problem>,
+    ) -> Result<Self> {
+        let mut ans = Self {
+            graph,
+            undo_stack,
+            redo_stack,
+            mode,
+            rule_set,
+            problem,
+            entries: Vec::new(),
+            tree: ListState::default().with_selected(Some(0)),
+            selected: Vec::new(),
+            menu: None,
+            prompt: None,
+            status: Ok("Press ? for help".to_string()),
+            history: Vec::new(),
+        };
+
+        ans.refresh()?;
+
+        Ok(ans)
+    }
+
+    /// takes over the terminal until the user quits
+    pub fn run(mut self) -> Result<()> {
+        let mut terminal = ratatui::try_init()?;
+        let res = self.event_loop(&mut terminal);
+        ratatui::restore();
+
+        res
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|f| self.draw(f))?;
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+
+                match self.handle(key) {
+                    Ok(true) => {}
+                    Ok(false) => break Ok(()),
+                    Err(e) => self.status = Err(e),
+                }
+            }
+        }
+    }
+
+    /// lists every area and atom in the order they're shown, keeping the cursor in range
+    fn refresh(&mut self) -> Result<()> {
+        self.entries.clear();
+
+        let mut stack = vec![*self.graph.root_id()];
+        while let Some(id) = stack.pop() {
+            self.entries.push(Entry::Area(id));
+
+            for atom in self
+                .graph
+                .atoms_of(&id)?
+                .iter()
+                .map(|a| a.to_string())
+                .sorted()
+            {
+                self.entries.push(Entry::Atom(id, atom));
+            }
+
+            stack.extend(self.graph.subgraphs_of(&id)?.iter().rev());
+        }
+
+        self.selected.retain(|v| self.entries.contains(v));
+
+        let cursor = self.tree.selected().unwrap_or(0);
+        self.tree.select(Some(cursor.min(self.entries.len() - 1)));
+
+        Ok(())
+    }
+
+    fn cursor(&self) -> &Entry {
+        &self.entries[self.tree.selected().unwrap_or(0)]
+    }
+
+    /// handles a key press, returning whether to keep going
+    fn handle(&mut self, key: KeyEvent) -> Result<bool> {
+        if let Some((prompt, text)) = &mut self.prompt {
+            match key.code {
+                KeyCode::Enter => {
+                    let (prompt, text) = (*prompt, text.clone());
+                    self.prompt = None;
+
+                    match prompt {
+                        Prompt::Insert(target) => {
+                            self.apply_rule(InferenceRule::Insertion(Insertion {
+                                target,
+                                new_content: text,
+                            }))?
+                        }
+                        Prompt::AddAtom(target) => self.apply_edit(
+                            [Action::AddAtom {
+                                target: GraphTarget::Exists(target),
+                                atom: Atom::from(text.as_str()),
+                            }],
+                            format!("Add {} to {}", text, target),
+                        )?,
+                    }
+                }
+                KeyCode::Esc => self.prompt = None,
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) => text.push(c),
+                _ => {}
+            }
+
+            return Ok(true);
+        }
+
+        if let Some((rules, state)) = &mut self.menu {
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => state.select_previous(),
+                KeyCode::Down | KeyCode::Char('j') => state.select_next(),
+                KeyCode::Enter => {
+                    let rule = state.selected().and_then(|i| rules.get(i)).cloned();
+                    self.menu = None;
+
+                    if let Some(rule) = rule {
+                        self.apply_rule(rule)?;
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('q') => self.menu = None,
+                _ => {}
+            }
+
+            return Ok(true);
+        }
+
+        match key.code {
+            KeyCode::Char('q') => return Ok(false),
+            KeyCode::Esc => self.selected.clear(),
+            KeyCode::Up | KeyCode::Char('k') => self.tree.select_previous(),
+            KeyCode::Down | KeyCode::Char('j')
+                if self
+                    .tree
+                    .selected()
+                    .is_some_and(|i| i + 1 < self.entries.len()) =>
+            {
+                self.tree.select_next()
+            }
+            KeyCode::Char(' ') => {
+                let entry = self.cursor().clone();
+
+                match self.selected.iter().position(|v| *v == entry) {
+                    Some(i) => {
+                        self.selected.remove(i);
+                    }
+                    None if entry == Entry::Area(*self.graph.root_id()) => {
+                        Err(eyre!("The sheet itself can't be selected"))?
+                    }
+                    None => self.selected.push(entry),
+                }
+            }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => self.redo()?,
+            KeyCode::Enter | KeyCode::Char('r') => {
+                let rules = self.candidates()?;
+
+                if rules.is_empty() {
+                    Err(eyre!("No rule applies to the selection"))?
+                }
+
+                self.menu = Some((rules, ListState::default().with_selected(Some(0))));
+            }
+            KeyCode::Char('i') => {
+                self.prompt = Some((Prompt::Insert(self.cursor().area()), String::new()))
+            }
+            KeyCode::Char('a') => {
+                self.check_editor()?;
+                self.prompt = Some((Prompt::AddAtom(self.cursor().area()), String::new()))
+            }
+            KeyCode::Char('n') => {
+                self.check_editor()?;
+                let target = self.cursor().area();
+
+                self.apply_edit(
+                    [Action::AddSubgraph {
+                        target: GraphTarget::Exists(target),
+                        new_subgraph: GraphTarget::Future(0),
+                    }],
+                    format!("Add a cut to {}", target),
+                )?
+            }
+            KeyCode::Char('d') => {
+                self.check_editor()?;
+
+                match self.cursor().clone() {
+                    Entry::Atom(k, atom) => self.apply_edit(
+                        [Action::DeleteAtom {
+                            target: GraphTarget::Exists(k),
+                            atom: Atom::from(atom.as_str()),
+                        }],
+                        format!("Delete {} from {}", atom, k),
+                    )?,
+                    Entry::Area(k) => self.apply_edit(
+                        [Action::DeleteSubgraph {
+                            target: GraphTarget::Exists(k),
+                        }],
+                        format!("Delete {}", k),
+                    )?,
+                }
+            }
+            KeyCode::Char('u') => self.undo()?,
+            KeyCode::Char('U') => self.redo()?,
+            KeyCode::Char('?') => self.status = Ok(
+                "j/k move, space select, enter rules, i insert, a/n/d edit, u/U undo/redo, q quit"
+                    .to_string(),
+            ),
+            _ => {}
+        }
+
+        Ok(true)
+    }
+
+    fn check_editor(&self) -> Result<()> {
+        if self.mode != &Mode::Editor {
+            Err(eyre!("Cannot edit graph in proof mode"))?
+        }
+
+        Ok(())
+    }
+
+    /// every rule that can be applied to the selection, or to what's under the cursor if nothing
+    /// is selected
+    fn candidates(&self) -> Result<Vec<InferenceRule>> {
+        let graph = &*self.graph;
+        let root = *graph.root_id();
+
+        let selection = if self.selected.is_empty() {
+            vec![self.cursor().clone()]
+        } else {
+            self.selected.clone()
+        };
+
+        let mut ans = Vec::new();
+
+        // an area on its own can also have a double cut drawn into it
+        if let [Entry::Area(k)] = selection[..] {
+            ans.extend(InferenceRule::applicable_to(
+                graph,
+                self.rule_set,
+                k,
+                vec![],
+                vec![],
+            ));
+        }
+
+        if selection.contains(&Entry::Area(root)) {
+            return Ok(ans);
+        }
+
+        let parents = selection
+            .iter()
+            .map(|v| v.parent(graph))
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Ok(parent) = parents.iter().all_equal_value() {
+            let atoms = selection
+                .iter()
+                .filter_map(|v| match v {
+                    Entry::Atom(_, a) => Some(Arc::new(Atom::from(a.as_str()))),
+                    Entry::Area(_) => None,
+                })
+                .collect_vec();
+            let subgraphs = selection
+                .iter()
+                .filter_map(|v| match v {
+                    Entry::Area(k) => Some(*k),
+                    Entry::Atom(..) => None,
+                })
+                .collect_vec();
+
+            ans.extend(InferenceRule::applicable_to(
+                graph,
+                self.rule_set,
+                *parent,
+                atoms,
+                subgraphs,
+            ));
+        }
+
+        Ok(ans)
+    }
+
+    fn apply_rule(&mut self, rule: InferenceRule) -> Result<()> {
+        let actions = rule.gen_actions_with_rules(self.graph, self.rule_set)?;
+        self.apply_edit(actions, rule.describe())
+    }
+
+    fn apply_edit(
+        &mut self,
+        actions: impl IntoIterator<Item = Action> + std::fmt::Debug,
+        description: String,
+    ) -> Result<()> {
+        self.undo_stack
+            .push_front(Action::apply_actions(actions, self.graph)?);
+        self.redo_stack.clear();
+        self.selected.clear();
+
+        self.status = Ok(match self.problem {
+            Some(problem) if problem.is_reached_by(self.graph)? => "Goal reached".to_string(),
+            _ => description.clone(),
+        });
+        self.history.push(description);
+
+        self.refresh()
+    }
+
+    fn undo(&mut self) -> Result<()> {
+        let things = self
+            .undo_stack
+            .pop_front()
+            .ok_or_else(|| eyre!("Nothing to undo"))?;
+        self.redo_stack
+            .push_front(Action::apply_actions(things, self.graph)?);
+
+        self.history.push("Undo".to_string());
+        self.refresh()
+    }
+
+    fn redo(&mut self) -> Result<()> {
+        let things = self
+            .redo_stack
+            .pop_front()
+            .ok_or_else(|| eyre!("Nothing to redo"))?;
+        self.undo_stack
+            .push_front(Action::apply_actions(things, self.graph)?);
+
+        self.history.push("Redo".to_string());
+        self.refresh()
+    }
+
+    fn draw(&mut self, f: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(f.area());
+        let [tree, side] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(main);
+        let [menu, history] =
+            Layout::vertical([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(side);
+
+        let items = self
+            .entries
+            .iter()
+            .map(|v| {
+                let level = self.graph.level_of(&v.area()).unwrap_or(0);
+                let mark = if self.selected.contains(v) { "*" } else { " " };
+
+                let text = match v {
+                    Entry::Area(k) if k == self.graph.root_id() => format!("{}sheet", mark),
+                    Entry::Area(k) => {
+                        format!("{}{}( {}", mark, "  ".repeat(level - 1), k)
+                    }
+                    Entry::Atom(_, a) => format!("{}{}{}", mark, "  ".repeat(level), a),
+                };
+
+                let item = ListItem::new(text);
+                if self.selected.contains(v) {
+                    item.style(Style::new().fg(Color::Yellow))
+                } else {
+                    item
+                }
+            })
+            .collect_vec();
+
+        f.render_stateful_widget(
+            List::new(items)
+                .block(Block::new().borders(Borders::ALL).title(" Graph "))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            tree,
+            &mut self.tree,
+        );
+
+        match &mut self.menu {
+            Some((rules, state)) => f.render_stateful_widget(
+                List::new(rules.iter().map(|v| v.describe()))
+                    .block(Block::new().borders(Borders::ALL).title(" Rules "))
+                    .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+                menu,
+                state,
+            ),
+            None => f.render_widget(
+                Paragraph::new(vec![
+                    Line::from(format!("Mode: {}", self.mode)),
+                    Line::from(format!("Selected: {}", self.selected.len())),
+                    Line::from(""),
+                    Line::from("enter  rules for the selection"),
+                    Line::from("i      insert into this area"),
+                    Line::from("space  select"),
+                    Line::from("u / U  undo / redo"),
+                ])
+                .block(Block::new().borders(Borders::ALL).title(" Rules ")),
+                menu,
+            ),
+        }
+
+        f.render_widget(
+            Paragraph::new(
+                self.history
+                    .iter()
+                    .rev()
+                    .map(|v| Line::from(v.as_str()))
+                    .collect_vec(),
+            )
+            .block(Block::new().borders(Borders::ALL).title(" History ")),
+            history,
+        );
+
+        let line = match (&self.prompt, &self.status) {
+            (Some((Prompt::Insert(k), text)), _) => {
+                Line::from(format!("Insert into {}: {}", k, text))
+            }
+            (Some((Prompt::AddAtom(k), text)), _) => {
+                Line::from(format!("Add atom to {}: {}", k, text))
+            }
+            (None, Ok(v)) => Line::from(v.as_str()),
+            (None, Err(e)) => Line::styled(e.to_string(), Style::new().fg(Color::Red)),
+        };
+
+        f.render_widget(Paragraph::new(line), status);
+    }
+}
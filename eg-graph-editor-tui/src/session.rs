@@ -0,0 +1,154 @@
+use crate::args::mode::Mode;
+use clap::ValueEnum;
+use color_eyre::{eyre::eyre, Report};
+use eg_graph_editor_lib::{
+    graph::{transform_graph_into_keyed_string, Graph},
+    proof::{action::Action, problem::Problem, rule_set::RuleSet},
+};
+use std::{
+    collections::{LinkedList, VecDeque},
+    fmt::Display,
+    str::FromStr,
+};
+
+/// everything needed to carry on with a session later, with the same ids
+pub struct Session {
+    pub graph: Graph,
+    pub mode: Mode,
+    pub rule_set: RuleSet,
+    pub problem: Option<Problem>,
+    pub undo_stack: VecDeque<LinkedList<Action>>,
+    pub redo_stack: VecDeque<LinkedList<Action>>,
+}
+
+/// writes the session as
+///
+/// ```text
+/// mode MODE
+/// graph KEY_STATE KEYED_GRAPH
+/// rules RULE_SET
+/// problem NAME
+/// ...
+/// end
+/// undo
+/// action ACTION
+/// ...
+/// redo
+/// action ACTION
+/// ...
+/// ```
+///
+/// where every `undo` or `redo` line starts one entry of its stack, most recent first, and the
+/// rules and problem are left out when there are none
+impl Display for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "mode {}", self.mode)?;
+        writeln!(
+            f,
+            "graph {} {}",
+            self.graph.key_state(),
+            transform_graph_into_keyed_string(&self.graph, self.graph.root_id())
+                .map_err(|_| std::fmt::Error)?
+        )?;
+
+        if self.rule_set != RuleSet::default() {
+            writeln!(f, "rules {}", self.rule_set)?;
+        }
+
+        if let Some(problem) = &self.problem {
+            write!(f, "{}", problem)?;
+        }
+
+        for (name, stack) in [("undo", &self.undo_stack), ("redo", &self.redo_stack)] {
+            for entry in stack {
+                writeln!(f, "{}", name)?;
+
+                for action in entry {
+                    writeln!(f, "action {}", action)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Session {
+    type Err = Report;
+
+    /// reads a session, as written by its [`Display`] implementation
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mode = None;
+        let mut graph = None;
+        let mut rule_set = None;
+        let mut problem = None;
+        let mut undo_stack = VecDeque::new();
+        let mut redo_stack = VecDeque::new();
+
+        // whether action lines go to the last undo or the last redo entry
+        let mut last = None;
+
+        let mut lines = s
+            .lines()
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty() && !v.starts_with('#'));
+
+        while let Some(line) = lines.next() {
+            let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let rest = rest.trim();
+
+            match word {
+                "mode" if mode.is_none() => {
+                    mode = Some(Mode::from_str(rest, true).map_err(|e| eyre!(e))?)
+                }
+                "graph" if graph.is_none() => {
+                    let (key_state, keyed) = rest
+                        .split_once(' ')
+                        .ok_or_else(|| eyre!("Expected a key state and a graph: {}", line))?;
+
+                    graph = Some(Graph::try_from(keyed.trim())?.with_key_state(key_state.parse()?))
+                }
+                "rules" if rule_set.is_none() => rule_set = Some(RuleSet::from_str(rest)?),
+                "problem" if problem.is_none() => {
+                    let block = [line]
+                        .into_iter()
+                        .chain(lines.by_ref().take_while(|v| *v != "end"))
+                        .chain(["end"])
+                        .collect::<Vec<_>>()
+                        .join("\n");
+
+                    problem = Some(Problem::from_str(&block)?)
+                }
+                "undo" => {
+                    undo_stack.push_back(LinkedList::new());
+                    last = Some(word);
+                }
+                "redo" => {
+                    redo_stack.push_back(LinkedList::new());
+                    last = Some(word);
+                }
+                "action" => {
+                    let entry = match last {
+                        Some("undo") => undo_stack.back_mut(),
+                        Some(_) => redo_stack.back_mut(),
+                        None => None,
+                    };
+
+                    entry
+                        .ok_or_else(|| eyre!("Action outside of an undo or redo: {}", line))?
+                        .push_back(Action::from_str(rest)?)
+                }
+                _ => Err(eyre!("Unexpected {}", line))?,
+            }
+        }
+
+        Ok(Session {
+            graph: graph.ok_or_else(|| eyre!("Session has no graph"))?,
+            mode: mode.unwrap_or(Mode::Editor),
+            rule_set: rule_set.unwrap_or_default(),
+            problem,
+            undo_stack,
+            redo_stack,
+        })
+    }
+}
@@ -0,0 +1,159 @@
+use crate::args::task::{RenderFormat, Task};
+use color_eyre::{eyre::eyre, Result};
+use eg_graph_editor_lib::{
+    graph::{
+        semantics::check,
+        syntax::{read_graph, write_graph, Syntax},
+        transform_graph_into_canonical_string,
+    },
+    proof::{problem::parse_problems, Proof},
+    render::{
+        dot::graph_to_dot,
+        svg::{graph_to_svg, SvgOptions},
+        text::{graph_to_text, TextOptions},
+    },
+};
+use itertools::Itertools;
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// runs a one-shot task, printing its result to stdout
+pub fn run(task: Task) -> Result<()> {
+    match task {
+        Task::Convert { from, to, input } => {
+            let graph = read_graph(&read_input(input)?, from.into())?;
+
+            println!("{}", write_graph(&graph, graph.root_id(), to.into())?);
+        }
+
+        Task::Check { from, input } => {
+            let graph = read_graph(&read_input(input)?, from.into())?;
+            let ans = check(&graph, graph.root_id())?;
+            let assignment = |v: &BTreeSet<String>| format!("{{{}}}", v.iter().join(", "));
+
+            println!("valid: {}", ans.valid);
+            println!("satisfiable: {}", ans.satisfiable());
+
+            if let Some(v) = &ans.model {
+                println!("true when exactly these atoms are true: {}", assignment(v));
+            }
+
+            if let Some(v) = &ans.counterexample {
+                println!("false when exactly these atoms are true: {}", assignment(v));
+            }
+        }
+
+        Task::Verify {
+            proof,
+            problem,
+            name,
+        } => {
+            let mut proof = Proof::from_str(&fs::read_to_string(proof)?)?;
+
+            let problem = match problem {
+                Some(path) => {
+                    let mut problems = parse_problems(&fs::read_to_string(path)?)?.into_iter();
+
+                    Some(match name {
+                        Some(name) => problems
+                            .find(|v| v.name() == name)
+                            .ok_or_else(|| eyre!("No problem named {}", name))?,
+                        None => problems
+                            .next()
+                            .ok_or_else(|| eyre!("No problems in file"))?,
+                    })
+                }
+                None => None,
+            };
+
+            // the problem decides which rules are allowed
+            if let Some(problem) = &problem {
+                proof = proof.with_rule_set(problem.rule_set().clone());
+
+                let sheet = problem.sheet()?;
+                if transform_graph_into_canonical_string(&sheet, sheet.root_id())?
+                    != transform_graph_into_canonical_string(
+                        proof.premise(),
+                        proof.premise().root_id(),
+                    )?
+                {
+                    Err(eyre!(
+                        "Proof does not start from the premises of {}",
+                        problem.name()
+                    ))?
+                }
+            }
+
+            let conclusion = proof.conclusion()?;
+
+            println!(
+                "valid: {} steps concluding {}",
+                proof.steps().len(),
+                write_graph(&conclusion, conclusion.root_id(), Syntax::Bracket)?
+            );
+
+            if let Some(problem) = &problem {
+                if !problem.is_reached_by(&conclusion)? {
+                    Err(eyre!("Proof does not reach the goal of {}", problem.name()))?
+                }
+
+                println!("reaches the goal of {}", problem.name());
+            }
+        }
+
+        Task::Render {
+            format,
+            from,
+            ids,
+            input,
+        } => {
+            let graph = read_graph(&read_input(input)?, from.into())?;
+            let id = graph.root_id();
+
+            match format {
+                RenderFormat::Svg => print!(
+                    "{}",
+                    graph_to_svg(
+                        &graph,
+                        id,
+                        &SvgOptions {
+                            show_ids: ids,
+                            ..Default::default()
+                        }
+                    )?
+                ),
+                RenderFormat::Dot => print!("{}", graph_to_dot(&graph, id)?),
+                RenderFormat::Text => print!(
+                    "{}",
+                    graph_to_text(
+                        &graph,
+                        id,
+                        &TextOptions {
+                            show_ids: ids,
+                            ..Default::default()
+                        }
+                    )?
+                ),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// the contents of `input`, or everything on stdin when there is no file
+fn read_input(input: Option<PathBuf>) -> Result<String> {
+    match input {
+        Some(path) => Ok(fs::read_to_string(path)?),
+        None => {
+            let mut ans = String::new();
+            io::stdin().read_to_string(&mut ans)?;
+            Ok(ans)
+        }
+    }
+}
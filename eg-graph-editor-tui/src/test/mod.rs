@@ -0,0 +1,140 @@
+use crate::{args::mode::Mode, complete::CommandHelper, session::Session};
+use color_eyre::Result;
+use eg_graph_editor_lib::{
+    atom::Atom,
+    graph::{transform_graph_into_keyed_string, Graph},
+    proof::{
+        action::{Action, GraphTarget},
+        problem::parse_problems,
+        rule_set::RuleSet,
+    },
+};
+use rustyline::{completion::Completer, history::MemHistory, Context};
+use std::{
+    collections::{LinkedList, VecDeque},
+    str::FromStr,
+};
+
+#[test]
+fn session_test() -> Result<()> {
+    // one atom to an area, since the order atoms are written in isn't fixed
+    let mut graph = Graph::try_from("[[B]]")?;
+    let undo = Action::apply_actions(
+        [Action::AddAtom {
+            target: GraphTarget::Exists(*graph.root_id()),
+            atom: Atom::from("A"),
+        }],
+        &mut graph,
+    )?;
+
+    let problem = parse_problems(
+        "
+        problem mp
+        premise [A]
+        premise [A, [A, [B]]]
+        goal [A, B]
+        end
+        ",
+    )?
+    .remove(0);
+
+    let session = Session {
+        graph,
+        mode: Mode::Proof,
+        rule_set: RuleSet::nested_iteration(),
+        problem: Some(problem),
+        undo_stack: VecDeque::from([undo, LinkedList::new()]),
+        redo_stack: VecDeque::new(),
+    };
+
+    let text = session.to_string();
+    assert!(text.contains("\nrules "));
+
+    let reread = Session::from_str(&text)?;
+    assert_eq!(reread.to_string(), text);
+
+    assert_eq!(reread.mode, Mode::Proof);
+    assert_eq!(reread.rule_set, RuleSet::nested_iteration());
+    assert_eq!(reread.graph.key_state(), session.graph.key_state());
+    assert_eq!(
+        transform_graph_into_keyed_string(&reread.graph, reread.graph.root_id())?,
+        transform_graph_into_keyed_string(&session.graph, session.graph.root_id())?
+    );
+    assert_eq!(
+        reread.problem.map(|v| v.to_string()),
+        session.problem.map(|v| v.to_string())
+    );
+
+    // the empty entry is kept, so undoing still takes as many steps
+    assert_eq!(reread.undo_stack.len(), 2);
+    assert_eq!(reread.undo_stack[0].len(), 1);
+    assert!(reread.undo_stack[1].is_empty());
+    assert!(reread.redo_stack.is_empty());
+
+    assert!(Session::from_str("mode proof").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn spaced_atom_session_test() -> Result<()> {
+    let mut graph = Graph::try_from("[[B]]")?;
+    let undo = Action::apply_actions(
+        [Action::AddAtom {
+            target: GraphTarget::Exists(*graph.root_id()),
+            atom: Atom::from("my atom"),
+        }],
+        &mut graph,
+    )?;
+
+    let session = Session {
+        graph,
+        mode: Mode::Editor,
+        rule_set: RuleSet::default(),
+        problem: None,
+        undo_stack: VecDeque::from([undo]),
+        redo_stack: VecDeque::new(),
+    };
+
+    let mut reread = Session::from_str(&session.to_string())?;
+    assert!(reread
+        .graph
+        .atoms_of(reread.graph.root_id())?
+        .iter()
+        .any(|a| **a == *"my atom"));
+
+    // the saved undo entry still names the atom the graph has
+    let undo = reread.undo_stack.pop_front().unwrap_or_default();
+    Action::apply_actions(undo, &mut reread.graph)?;
+    assert!(reread.graph.atoms_of(reread.graph.root_id())?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn complete_test() -> Result<()> {
+    let mut helper = CommandHelper::new();
+    CommandHelper::update(&mut helper, &Graph::try_from("[Apple, [Banana]]")?);
+
+    let history = MemHistory::new();
+    let ctx = Context::new(&history);
+    let complete = |line: &str| -> Result<Vec<String>> {
+        let (_, pairs) = helper.complete(line, line.len(), &ctx)?;
+        Ok(pairs.into_iter().map(|v| v.replacement).collect())
+    };
+
+    // subcommands are only completed after their parent
+    assert!(complete("")?.contains(&"edit".to_string()));
+    assert!(!complete("")?.contains(&"new-atom".to_string()));
+    assert!(complete("edit ")?.contains(&"new-atom".to_string()));
+    assert!(complete("e n")?.contains(&"na".to_string()));
+    assert!(!complete("e ")?.contains(&"edit".to_string()));
+
+    // arguments are ids and atoms, never commands
+    assert!(complete("edit new-atom A")?.contains(&"Apple".to_string()));
+    assert!(!complete("edit new-atom 0 e")?.contains(&"edit".to_string()));
+    assert!(complete("nothing B")?.contains(&"Banana".to_string()));
+    assert!(!complete("nothing B")?.contains(&"Apple".to_string()));
+
+    Ok(())
+}